@@ -3,6 +3,7 @@ let s:timer = 0
 let s:debug_bufnr = 0
 let s:server_started = 0
 let s:server_retries = 0
+let s:hovering = 0
 
 let s:dir = expand('<sfile>:p:h')
 
@@ -94,6 +95,7 @@ function! picomap#show() abort
 	let l:success = s:start_server()
 
 	call rpcnotify(s:ch, 'show')
+	call picomap#quickfix()
 
 	if l:success
 		call s:timer_start()
@@ -124,12 +126,156 @@ function! picomap#sync() abort
 	call rpcnotify(s:ch, 'sync', l:diags, l:changes)
 endfunction
 
+function! picomap#quickfix() abort
+	if s:ch > 0
+		call rpcnotify(s:ch, 'quickfix', getqflist())
+	endif
+endfunction
+
+" send the keyword under the cursor, or an empty word when not on a keyword
+function! picomap#occurrence() abort
+	if s:ch > 0
+		let l:word = matchstr(getline('.'), '\k*\%' . col('.') . 'c\k\+')
+		call rpcnotify(s:ch, 'occurrence', l:word)
+	endif
+endfunction
+
+function! picomap#outline() abort
+	if s:ch > 0
+		call rpcnotify(s:ch, 'outline')
+	endif
+endfunction
+
+function! picomap#blame() abort
+	if s:ch > 0
+		call rpcnotify(s:ch, 'blame')
+	endif
+endfunction
+
+" move to the next unresolved merge conflict of the buffer
+function! picomap#next_conflict() abort
+	if s:ch > 0
+		call rpcnotify(s:ch, 'jump_conflict', 1)
+	endif
+endfunction
+
+function! picomap#prev_conflict() abort
+	if s:ch > 0
+		call rpcnotify(s:ch, 'jump_conflict', -1)
+	endif
+endfunction
+
 function! picomap#resize()
 	if s:ch > 0
 		call rpcnotify(s:ch, 'resize')
 	endif
 endfunction
 
+" deep merge src into dst
+function! s:merge(dst, src) abort
+	for [l:key, l:value] in items(a:src)
+		if type(l:value) == v:t_dict && type(get(a:dst, l:key, 0)) == v:t_dict
+			call s:merge(a:dst[l:key], l:value)
+		else
+			let a:dst[l:key] = l:value
+		endif
+	endfor
+endfunction
+
+function! picomap#configure(config) abort
+	call s:merge(g:picomap_config, a:config)
+
+	if s:ch > 0
+		call rpcnotify(s:ch, 'configure', a:config)
+	endif
+endfunction
+
+" cursor column and the extent of each visible non-blank line,
+" in display columns relative to the text area of the current window
+function! picomap#extent() abort
+	let l:leftcol = winsaveview().leftcol
+	let l:lines = []
+
+	for l:lnum in range(line('w0'), line('w$'))
+		if getline(l:lnum) =~# '\S'
+			call add(l:lines, [
+				\ max([indent(l:lnum) - l:leftcol, 0]),
+				\ max([virtcol([l:lnum, '$']) - 1 - l:leftcol, 0]),
+				\ ])
+		endif
+	endfor
+
+	return {'cursor': max([virtcol('.') - 1 - l:leftcol, 0]), 'lines': l:lines}
+endfunction
+
+" closed folds of the current window as [start, end] lines.
+" they are kept until the buffer or the visible lines change, which opening or closing
+" a fold does unless the whole buffer fits in the window.
+function! picomap#folds() abort
+	let l:key = [bufnr(), b:changedtick, line('w0'), line('w$'), &foldenable]
+
+	if line('$') > winheight(0) && get(w:, 'picomap_folds_key', []) ==# l:key
+		return w:picomap_folds
+	endif
+
+	let l:folds = []
+	let l:lnum = 1
+	let l:last = line('$')
+
+	while l:lnum <= l:last
+		let l:end = foldclosedend(l:lnum)
+
+		if l:end > 0
+			call add(l:folds, [l:lnum, l:end])
+			let l:lnum = l:end + 1
+		else
+			let l:lnum += 1
+		endif
+	endwhile
+
+	let w:picomap_folds_key = l:key
+	let w:picomap_folds = l:folds
+
+	return l:folds
+endfunction
+
+function! picomap#evict(bufnr)
+	if s:ch > 0
+		call rpcnotify(s:ch, 'evict', a:bufnr)
+	endif
+endfunction
+
+" describe the map row under the mouse, or the given 1-based row of the map
+function! picomap#hover(...) abort
+	if s:ch == 0
+		return
+	endif
+
+	if a:0 > 0
+		let l:row = a:1
+	else
+		let l:pos = getmousepos()
+
+		if getwinvar(l:pos.winid, '&filetype') !=# 'picomap'
+			call picomap#unhover()
+			return
+		endif
+
+		let l:row = l:pos.line
+	endif
+
+	let s:hovering = 1
+
+	call rpcnotify(s:ch, 'hover', l:row)
+endfunction
+
+function! picomap#unhover() abort
+	if s:ch > 0 && s:hovering
+		let s:hovering = 0
+		call rpcnotify(s:ch, 'unhover')
+	endif
+endfunction
+
 function! picomap#hide()
 	call s:timer_stop()
 	if s:ch > 0
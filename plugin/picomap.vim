@@ -11,6 +11,13 @@ augroup PicomapUpdate
 	autocmd WinEnter * call picomap#resize()
 	autocmd WinLeave * call picomap#resize()
 	autocmd WinNew * call picomap#resize()
+	autocmd BufEnter * call picomap#resize()
+	autocmd BufWipeout,BufUnload * call picomap#evict(str2nr(expand('<abuf>')))
+	autocmd QuickFixCmdPost [^l]* call picomap#quickfix()
+	autocmd CursorMoved,CursorMovedI,WinLeave * call picomap#unhover()
+	autocmd CursorHold,CursorHoldI * call picomap#occurrence()
+	autocmd BufEnter,BufWritePost,InsertLeave,CursorHold * call picomap#outline()
+	autocmd BufEnter,BufWritePost,CursorHold * call picomap#blame()
 augroup END
 
 augroup Picomap
@@ -20,10 +27,10 @@ augroup Picomap
 augroup END
 
 let g:picomap_autostart = 1
-let g:picomap_winblend = 30
 let g:picomap_sync_interval = 100
 let g:picomap_gitgutter = 1
 let g:picomap_coc = 1
+let g:picomap_config = get(g:, 'picomap_config', {})
 
 let s:bin_suffix = has('win32') ? '.exe' : ''
 let s:env = 'debug'
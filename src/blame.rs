@@ -0,0 +1,209 @@
+use crate::cache::Cache;
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+use std::sync::{Mutex, PoisonError};
+
+/// a commit which last changed some lines
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Commit {
+    pub author: String,
+    /// unix time the commit was authored
+    pub time: u64,
+}
+
+/// commits of each line of a file given by `git blame`
+#[derive(Debug, Default)]
+pub struct Blame {
+    commits: Vec<Commit>,
+    /// 1-based line and index of its commit
+    lines: Vec<(u64, usize)>,
+}
+
+impl Blame {
+    /// parse the output of `git blame --porcelain`.
+    /// the author of a commit is only given on its first line.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut blame = Blame::default();
+        let mut shas: HashMap<&str, usize> = HashMap::new();
+        let mut current: Option<usize> = None;
+
+        for (n, line) in text.lines().enumerate() {
+            if line.starts_with('\t') {
+                continue;
+            }
+
+            let mut words = line.splitn(2, ' ');
+            let key = words.next().unwrap_or("");
+            let value = words.next().unwrap_or("");
+
+            match key {
+                "author" => {
+                    if let Some(i) = current {
+                        blame.commits[i].author = value.to_string();
+                    }
+                }
+                "author-time" => {
+                    if let Some(i) = current {
+                        blame.commits[i].time = value
+                            .parse()
+                            .with_context(|| format!("invalid author time at line {}", n + 1))?;
+                    }
+                }
+                sha if sha.len() == 40 && sha.chars().all(|c| c.is_ascii_hexdigit()) => {
+                    // `<sha> <original line> <final line> [<lines in group>]`
+                    let lnum = value
+                        .split(' ')
+                        .nth(1)
+                        .and_then(|lnum| lnum.parse().ok())
+                        .with_context(|| format!("invalid header at line {}: {}", n + 1, line))?;
+
+                    let next = blame.commits.len();
+                    let i = *shas.entry(sha).or_insert(next);
+
+                    if i == next {
+                        blame.commits.push(Commit::default());
+                    }
+
+                    blame.lines.push((lnum, i));
+                    current = Some(i);
+                }
+                _ => {}
+            }
+        }
+
+        Ok(blame)
+    }
+
+    /// run `git blame` for the file at `path`, which is empty when the file is not tracked
+    pub fn run(path: &str) -> Result<Self> {
+        let output = git(path, &["blame", "--porcelain", "--", path])?;
+
+        if !output.status.success() {
+            return Ok(Blame::default());
+        }
+
+        Blame::parse(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    /// 1-based lines of the file and their commits
+    pub fn lines(&self) -> Vec<(u64, &Commit)> {
+        self.lines
+            .iter()
+            .map(|(lnum, i)| (*lnum, &self.commits[*i]))
+            .collect()
+    }
+}
+
+/// blob id of the content of the file at `path`
+pub fn blob_id(path: &str) -> Result<String> {
+    let output = git(path, &["hash-object", "--", path])?;
+
+    if !output.status.success() {
+        bail!(
+            "failed to hash {}: {}",
+            path,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// 1-based lines of the file at `path` and their commits, blamed again only when
+/// the blob id of the content is not in `blames`
+pub fn blame_cached(
+    path: &str,
+    blames: &Mutex<Cache<String, Blame>>,
+) -> Result<Vec<(u64, Commit)>> {
+    let blob = blob_id(path)?;
+
+    let cached = blames
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .contains(&blob);
+
+    // git runs without holding the lock
+    if !cached {
+        let blame = Blame::run(path)?;
+
+        blames
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get_or_insert_with(blob.clone(), || blame);
+    }
+
+    Ok(blames
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(&blob)
+        .map(|blame| {
+            blame
+                .lines()
+                .into_iter()
+                .map(|(lnum, commit)| (lnum, commit.clone()))
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+/// run git in the directory of the file at `path`
+fn git(path: &str, args: &[&str]) -> Result<std::process::Output> {
+    let dir = Path::new(path)
+        .parent()
+        .filter(|dir| dir.is_dir())
+        .with_context(|| format!("no directory of {}", path))?;
+
+    Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .context("failed to run git")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blame_parse() {
+        let blame = Blame::parse(
+            "1111111111111111111111111111111111111111 1 1 2
+author Alice
+author-mail <alice@example.com>
+author-time 1600000000
+author-tz +0900
+summary init
+filename src/main.rs
+\tfn main() {
+1111111111111111111111111111111111111111 2 2
+\t}
+2222222222222222222222222222222222222222 3 3 1
+author Bob
+author-time 1700000000
+filename src/main.rs
+\t// author Eve
+",
+        )
+        .unwrap();
+
+        let alice = Commit {
+            author: "Alice".to_string(),
+            time: 1600000000,
+        };
+        let bob = Commit {
+            author: "Bob".to_string(),
+            time: 1700000000,
+        };
+
+        assert_eq!(blame.lines(), vec![(1, &alice), (2, &alice), (3, &bob)]);
+
+        assert!(Blame::parse(
+            "1111111111111111111111111111111111111111 1 x 1
+author-time 1
+"
+        )
+        .is_err());
+    }
+}
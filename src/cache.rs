@@ -0,0 +1,138 @@
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+
+/// least recently used cache, dropping the oldest entry when it is full
+pub struct Cache<K, V> {
+    capacity: usize,
+    values: HashMap<K, V>,
+    order: VecDeque<K>,
+}
+
+impl<K: Eq + Hash + Clone, V> Cache<K, V> {
+    pub fn new(capacity: usize) -> Self {
+        Cache {
+            capacity,
+            values: HashMap::with_capacity(capacity),
+            order: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.values.contains_key(key)
+    }
+
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        if !self.values.contains_key(key) {
+            return None;
+        }
+
+        self.touch(key);
+
+        self.values.get(key)
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        if !self.values.contains_key(key) {
+            return None;
+        }
+
+        self.touch(key);
+
+        self.values.get_mut(key)
+    }
+
+    pub fn get_or_insert_with<F: FnOnce() -> V>(&mut self, key: K, f: F) -> &mut V {
+        if self.values.contains_key(&key) {
+            self.touch(&key);
+        } else {
+            while self.values.len() >= self.capacity.max(1) {
+                self.evict();
+            }
+
+            self.order.push_back(key.clone());
+            self.values.insert(key.clone(), f());
+        }
+
+        self.values.get_mut(&key).expect("cache entry must exist")
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        self.order.retain(|k| k != key);
+
+        self.values.remove(key)
+    }
+
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+
+        while self.values.len() > self.capacity.max(1) {
+            self.evict();
+        }
+    }
+
+    /// every value, without changing the order of use
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut V> {
+        self.values.values_mut()
+    }
+
+    fn touch(&mut self, key: &K) {
+        if let Some(i) = self.order.iter().position(|k| k == key) {
+            if let Some(k) = self.order.remove(i) {
+                self.order.push_back(k);
+            }
+        }
+    }
+
+    fn evict(&mut self) {
+        if let Some(key) = self.order.pop_front() {
+            self.values.remove(&key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_evicts_least_recently_used() {
+        let mut cache = Cache::new(2);
+
+        cache.get_or_insert_with(1, || "a");
+        cache.get_or_insert_with(2, || "b");
+
+        assert_eq!(cache.get(&1), Some(&"a"));
+
+        cache.get_or_insert_with(3, || "c");
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.contains(&1));
+        assert!(!cache.contains(&2));
+        assert!(cache.contains(&3));
+    }
+
+    #[test]
+    fn test_cache_remove_and_set_capacity() {
+        let mut cache = Cache::new(3);
+
+        cache.get_or_insert_with(1, || "a");
+        cache.get_or_insert_with(2, || "b");
+        cache.get_or_insert_with(3, || "c");
+
+        assert_eq!(cache.remove(&2), Some("b"));
+        assert_eq!(cache.get(&2), None);
+
+        cache.set_capacity(1);
+
+        assert_eq!(cache.len(), 1);
+        assert!(cache.contains(&3));
+    }
+}
@@ -0,0 +1,1567 @@
+use crate::extmarks::ExtmarkRule;
+use crate::message::{TextExtent, WinInfo};
+use crate::picomap::ID_WIDTH;
+use crate::rules::RegexRule;
+use crate::signs::SignRule;
+use anyhow::{bail, Context, Result};
+use neovim_lib::Value;
+use regex::Regex;
+use std::cmp::max;
+use std::convert::TryFrom;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Color {
+    pub cterm: u64,
+    pub gui: String,
+}
+
+impl Color {
+    fn new(cterm: u64, gui: &str) -> Self {
+        Color {
+            cterm,
+            gui: gui.to_string(),
+        }
+    }
+
+    fn update(&mut self, value: &Value) -> Result<()> {
+        for (key, value) in fields(value)? {
+            match key.as_str().with_context(|| "invalid color key")? {
+                "cterm" => self.cterm = to_u64(value, "cterm")?,
+                "gui" => self.gui = to_string(value, "gui")?,
+                key => bail!("unknown color key: {}", key),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.cterm > 255 {
+            bail!("cterm color must be less than 256: {}", self.cterm);
+        }
+
+        let hex = self.gui.strip_prefix('#').unwrap_or("");
+
+        if self.gui != "NONE" && (hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit())) {
+            bail!("gui color must be NONE or #rrggbb: {}", self.gui);
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Colors {
+    pub cursor: Color,
+    pub view: Color,
+    pub select: Color,
+    pub select_line: Color,
+    pub select_block: Color,
+}
+
+impl Default for Colors {
+    fn default() -> Self {
+        Colors {
+            cursor: Color::new(237, "#2a3158"),
+            view: Color::new(235, "#1e2132"),
+            select: Color::new(243, "#757ca3"),
+            select_line: Color::new(241, "#6b7089"),
+            select_block: Color::new(60, "#5a6895"),
+        }
+    }
+}
+
+impl Colors {
+    fn update(&mut self, value: &Value) -> Result<()> {
+        for (key, value) in fields(value)? {
+            match key.as_str().with_context(|| "invalid colors key")? {
+                "cursor" => self.cursor.update(value).context("invalid cursor color")?,
+                "view" => self.view.update(value).context("invalid view color")?,
+                "select" => self.select.update(value).context("invalid select color")?,
+                "select_line" => self
+                    .select_line
+                    .update(value)
+                    .context("invalid select_line color")?,
+                "select_block" => self
+                    .select_block
+                    .update(value)
+                    .context("invalid select_block color")?,
+                key => bail!("unknown colors key: {}", key),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<()> {
+        for (_, _, color) in self.modifiers().iter() {
+            if let Some(color) = color {
+                color.validate()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// modifier character rendered by the map, group suffix and background of each modifier
+    fn modifiers(&self) -> [(char, &'static str, Option<&Color>); 6] {
+        [
+            (' ', "", None),
+            ('c', "cursor", Some(&self.cursor)),
+            ('v', "view", Some(&self.view)),
+            ('s', "select", Some(&self.select)),
+            ('l', "selectline", Some(&self.select_line)),
+            ('b', "selectblock", Some(&self.select_block)),
+        ]
+    }
+}
+
+/// a highlighter drawn on the map, with one color for each of its levels
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerConfig {
+    pub name: String,
+    pub enabled: bool,
+    pub column: u64,
+    pub colors: Vec<Color>,
+}
+
+impl LayerConfig {
+    fn new(name: &str, column: u64, colors: Vec<Color>) -> Self {
+        LayerConfig {
+            name: name.to_string(),
+            enabled: true,
+            column,
+            colors,
+        }
+    }
+
+    fn update(&mut self, value: &Value) -> Result<()> {
+        for (key, value) in fields(value)? {
+            match key.as_str().with_context(|| "invalid layer key")? {
+                "enabled" => {
+                    self.enabled = value
+                        .as_bool()
+                        .with_context(|| "enabled must be a boolean")?
+                }
+                "column" => self.column = to_u64(value, "column")?,
+                "colors" => update_levels(&mut self.colors, value).context("invalid colors")?,
+                key => bail!("unknown layer key: {}", key),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn default_layers() -> Vec<LayerConfig> {
+    vec![
+        LayerConfig::new(
+            "outline",
+            0,
+            vec![Color::new(238, "#3e445e"), Color::new(240, "#545c8c")],
+        ),
+        LayerConfig::new("change", 0, vec![Color::new(64, "#668e3d")]),
+        LayerConfig::new(
+            "occurrence",
+            0,
+            vec![
+                Color::new(110, "#84a0c6"),
+                Color::new(110, "#84a0c6"),
+                Color::new(150, "#b4be82"),
+            ],
+        ),
+        // the changelist sits on changed lines, which it would hide
+        LayerConfig {
+            enabled: false,
+            ..LayerConfig::new(
+                "history",
+                0,
+                vec![
+                    Color::new(60, "#5f5f87"),
+                    Color::new(67, "#5f87af"),
+                    Color::new(117, "#87d7ff"),
+                ],
+            )
+        },
+        // the marks of the last change sit on changed lines, which it would hide
+        LayerConfig {
+            enabled: false,
+            ..LayerConfig::new(
+                "marks",
+                0,
+                vec![
+                    Color::new(242, "#6b7089"),
+                    Color::new(109, "#89b8c2"),
+                    Color::new(140, "#a093c7"),
+                ],
+            )
+        },
+        LayerConfig::new(
+            "conflict",
+            0,
+            vec![
+                Color::new(245, "#818596"),
+                Color::new(110, "#84a0c6"),
+                Color::new(140, "#a093c7"),
+                Color::new(150, "#b4be82"),
+            ],
+        ),
+        // edited lines are also changed lines, which it would hide
+        LayerConfig {
+            enabled: false,
+            ..LayerConfig::new(
+                "recent",
+                0,
+                vec![
+                    Color::new(94, "#875f00"),
+                    Color::new(136, "#af8700"),
+                    Color::new(178, "#d7af00"),
+                    Color::new(220, "#ffd700"),
+                ],
+            )
+        },
+        LayerConfig {
+            enabled: false,
+            ..LayerConfig::new(
+                "blame",
+                1,
+                vec![
+                    Color::new(60, "#5f5f87"),
+                    Color::new(67, "#5f87af"),
+                    Color::new(108, "#87af87"),
+                    Color::new(179, "#d7af5f"),
+                    Color::new(209, "#ff875f"),
+                    Color::new(203, "#ff5f5f"),
+                ],
+            )
+        },
+        LayerConfig::new(
+            "profile",
+            1,
+            vec![
+                Color::new(58, "#5f5f00"),
+                Color::new(100, "#878700"),
+                Color::new(142, "#afaf00"),
+                Color::new(178, "#d7af00"),
+                Color::new(214, "#ffaf00"),
+                Color::new(208, "#ff8700"),
+                Color::new(202, "#ff5f00"),
+                Color::new(196, "#ff0000"),
+            ],
+        ),
+        LayerConfig::new(
+            "coverage",
+            1,
+            vec![Color::new(179, "#e2a478"), Color::new(203, "#e27878")],
+        ),
+        LayerConfig::new(
+            "annotation",
+            1,
+            default_annotations()
+                .into_iter()
+                .map(|(_, color)| color)
+                .collect(),
+        ),
+        LayerConfig::new(
+            "spell",
+            1,
+            vec![Color::new(109, "#89b8c2"), Color::new(203, "#e27878")],
+        ),
+        LayerConfig::new(
+            "format",
+            1,
+            vec![
+                Color::new(103, "#8389a3"),
+                Color::new(179, "#e2a478"),
+                Color::new(140, "#a093c7"),
+                Color::new(203, "#e27878"),
+            ],
+        ),
+        LayerConfig::new(
+            "quickfix",
+            1,
+            vec![
+                Color::new(103, "#8389a3"),
+                Color::new(179, "#e2a478"),
+                Color::new(203, "#e27878"),
+            ],
+        ),
+        LayerConfig::new(
+            "tests",
+            1,
+            vec![Color::new(168, "#d7669b"), Color::new(196, "#ff0000")],
+        ),
+        LayerConfig::new(
+            "diag",
+            1,
+            vec![Color::new(130, "#c57339"), Color::new(125, "#cc517a")],
+        ),
+        LayerConfig::new("fold", 0, vec![Color::new(242, "#6b7089")]),
+    ]
+}
+
+/// keywords of the annotation layer and their colors
+fn default_annotations() -> Vec<(&'static str, Color)> {
+    vec![
+        ("TODO", Color::new(179, "#e2a478")),
+        ("FIXME", Color::new(203, "#e27878")),
+        ("HACK", Color::new(140, "#a093c7")),
+        ("XXX", Color::new(203, "#e27878")),
+        ("SAFETY", Color::new(109, "#89b8c2")),
+    ]
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+impl Side {
+    pub fn opposite(&self) -> Self {
+        match self {
+            Side::Left => Side::Right,
+            Side::Right => Side::Left,
+        }
+    }
+}
+
+impl TryFrom<&str> for Side {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &str) -> Result<Self> {
+        match value {
+            "left" => Ok(Side::Left),
+            "right" => Ok(Side::Right),
+            _ => bail!("side must be left or right: {}", value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Relative {
+    Editor,
+    Win,
+}
+
+impl TryFrom<&str> for Relative {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &str) -> Result<Self> {
+        match value {
+            "editor" => Ok(Relative::Editor),
+            "win" => Ok(Relative::Win),
+            _ => bail!("relative must be editor or win: {}", value),
+        }
+    }
+}
+
+/// what to do with the map while it covers the cursor or text
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Dodge {
+    None,
+    Blend,
+    Move,
+    Hide,
+}
+
+impl TryFrom<&str> for Dodge {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &str) -> Result<Self> {
+        match value {
+            "none" => Ok(Dodge::None),
+            "blend" => Ok(Dodge::Blend),
+            "move" => Ok(Dodge::Move),
+            "hide" => Ok(Dodge::Hide),
+            _ => bail!("dodge must be none, blend, move or hide: {}", value),
+        }
+    }
+}
+
+/// lines the rows of the map stand for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Lines {
+    Buffer,
+    /// display lines, where each closed fold takes a single line
+    Folds,
+}
+
+impl TryFrom<&str> for Lines {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &str) -> Result<Self> {
+        match value {
+            "buffer" => Ok(Lines::Buffer),
+            "folds" => Ok(Lines::Folds),
+            _ => bail!("lines must be buffer or folds: {}", value),
+        }
+    }
+}
+
+const MAX_WIDTH: u64 = 8;
+
+const BORDERS: [&str; 6] = ["none", "single", "double", "rounded", "solid", "shadow"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub winblend: u64,
+    pub width: u64,
+    pub side: Side,
+    pub relative: Relative,
+    pub offset: u64,
+    pub border: String,
+    pub title: String,
+    pub zindex: u64,
+    pub dodge: Dodge,
+    pub dodge_winblend: u64,
+    pub lines: Lines,
+    pub include_filetypes: Vec<String>,
+    pub exclude_filetypes: Vec<String>,
+    pub include_buftypes: Vec<String>,
+    pub exclude_buftypes: Vec<String>,
+    pub min_height: u64,
+    pub min_width: u64,
+    pub min_lines: u64,
+    pub max_buffers: u64,
+    pub annotations: Vec<String>,
+    pub coverage: String,
+    pub profile: String,
+    pub test_results: String,
+    pub recent_decay: u64,
+    /// rules of the sign layers `sign1`, `sign2`, ... in order
+    pub signs: Vec<SignRule>,
+    /// rules of the extmark layers `extmark1`, `extmark2`, ... in order
+    pub extmarks: Vec<ExtmarkRule>,
+    /// rules of the regex layers `rule1`, `rule2`, ... in order
+    pub rules: Vec<RegexRule>,
+    pub colors: Colors,
+    pub layers: Vec<LayerConfig>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            winblend: 30,
+            width: 2,
+            side: Side::Right,
+            relative: Relative::Editor,
+            offset: 0,
+            border: "none".to_string(),
+            title: String::new(),
+            zindex: 50,
+            dodge: Dodge::Blend,
+            dodge_winblend: 80,
+            lines: Lines::Buffer,
+            include_filetypes: vec![],
+            exclude_filetypes: to_owned(&[
+                "help", "qf", "netrw", "nerdtree", "NvimTree", "fugitive",
+            ]),
+            include_buftypes: vec![],
+            exclude_buftypes: to_owned(&["help", "quickfix", "terminal", "prompt", "nofile"]),
+            min_height: 5,
+            min_width: 20,
+            min_lines: 1,
+            max_buffers: 20,
+            annotations: default_annotations()
+                .into_iter()
+                .map(|(keyword, _)| keyword.to_string())
+                .collect(),
+            coverage: String::new(),
+            profile: String::new(),
+            test_results: String::new(),
+            recent_decay: 600,
+            signs: vec![],
+            extmarks: vec![],
+            rules: vec![],
+            colors: Colors::default(),
+            layers: default_layers(),
+        }
+    }
+}
+
+impl Config {
+    /// apply the keys present in `value` on top of the current config.
+    /// the config is left untouched when the result is invalid.
+    pub fn update(&mut self, value: &Value) -> Result<()> {
+        let mut config = self.clone();
+
+        // the keys replacing layers come first, so that `layers` can refer to them
+        let mut keys = fields(value)?.iter().collect::<Vec<_>>();
+        keys.sort_by_key(|(key, _)| {
+            !matches!(
+                key.as_str(),
+                Some("annotations") | Some("signs") | Some("extmarks") | Some("rules")
+            )
+        });
+
+        for (key, value) in keys {
+            match key.as_str().with_context(|| "invalid config key")? {
+                "winblend" => config.winblend = to_u64(value, "winblend")?,
+                "width" => config.width = to_u64(value, "width")?,
+                "side" => config.side = Side::try_from(&to_string(value, "side")?[..])?,
+                "relative" => {
+                    config.relative = Relative::try_from(&to_string(value, "relative")?[..])?
+                }
+                "offset" => config.offset = to_u64(value, "offset")?,
+                "border" => config.border = to_string(value, "border")?,
+                "title" => config.title = to_string(value, "title")?,
+                "zindex" => config.zindex = to_u64(value, "zindex")?,
+                "dodge" => config.dodge = Dodge::try_from(&to_string(value, "dodge")?[..])?,
+                "dodge_winblend" => config.dodge_winblend = to_u64(value, "dodge_winblend")?,
+                "lines" => config.lines = Lines::try_from(&to_string(value, "lines")?[..])?,
+                "include_filetypes" => {
+                    config.include_filetypes = to_strings(value, "include_filetypes")?
+                }
+                "exclude_filetypes" => {
+                    config.exclude_filetypes = to_strings(value, "exclude_filetypes")?
+                }
+                "include_buftypes" => {
+                    config.include_buftypes = to_strings(value, "include_buftypes")?
+                }
+                "exclude_buftypes" => {
+                    config.exclude_buftypes = to_strings(value, "exclude_buftypes")?
+                }
+                "min_height" => config.min_height = to_u64(value, "min_height")?,
+                "min_width" => config.min_width = to_u64(value, "min_width")?,
+                "min_lines" => config.min_lines = to_u64(value, "min_lines")?,
+                "max_buffers" => config.max_buffers = to_u64(value, "max_buffers")?,
+                "annotations" => {
+                    let annotations = to_annotations(value).context("invalid annotations")?;
+
+                    config.annotations = annotations.iter().map(|(k, _)| k.clone()).collect();
+                    config
+                        .layers
+                        .iter_mut()
+                        .find(|layer| layer.name == "annotation")
+                        .with_context(|| "unknown layer: annotation")?
+                        .colors = annotations.into_iter().map(|(_, color)| color).collect();
+                }
+                "coverage" => config.coverage = to_string(value, "coverage")?,
+                "profile" => config.profile = to_string(value, "profile")?,
+                "test_results" => config.test_results = to_string(value, "test_results")?,
+                "recent_decay" => config.recent_decay = to_u64(value, "recent_decay")?,
+                "signs" => {
+                    let rules =
+                        to_rules(value, "sign", ["name", "group"]).context("invalid signs")?;
+
+                    config.signs = replace_rule_layers(&mut config.layers, "sign", &rules)
+                        .into_iter()
+                        .map(|[name, group]| SignRule { name, group })
+                        .collect();
+                }
+                "extmarks" => {
+                    let rules = to_rules(value, "extmark", ["namespace", "hl_group"])
+                        .context("invalid extmarks")?;
+
+                    config.extmarks = replace_rule_layers(&mut config.layers, "extmark", &rules)
+                        .into_iter()
+                        .map(|[namespace, hl_group]| ExtmarkRule {
+                            namespace,
+                            hl_group,
+                        })
+                        .collect();
+                }
+                "rules" => {
+                    let rules = to_regex_rules(value).context("invalid rules")?;
+
+                    config.rules = replace_rule_layers(&mut config.layers, "rule", &rules);
+                }
+                "colors" => config.colors.update(value).context("invalid colors")?,
+                "layers" => {
+                    for (name, value) in fields(value)? {
+                        let name = name.as_str().with_context(|| "invalid layer name")?;
+
+                        config
+                            .layers
+                            .iter_mut()
+                            .find(|layer| layer.name == name)
+                            .with_context(|| format!("unknown layer: {}", name))?
+                            .update(value)
+                            .with_context(|| format!("invalid layer {}", name))?;
+                    }
+                }
+                key => bail!("unknown config key: {}", key),
+            }
+        }
+
+        config.validate()?;
+
+        *self = config;
+
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.winblend > 100 {
+            bail!("winblend must be between 0 and 100: {}", self.winblend);
+        }
+
+        if self.dodge_winblend > 100 {
+            bail!(
+                "dodge_winblend must be between 0 and 100: {}",
+                self.dodge_winblend
+            );
+        }
+
+        if self.width < 1 || self.width > MAX_WIDTH {
+            bail!("width must be between 1 and {}: {}", MAX_WIDTH, self.width);
+        }
+
+        if !BORDERS.contains(&&self.border[..]) {
+            bail!(
+                "border must be one of {}: {}",
+                BORDERS.join(", "),
+                self.border
+            );
+        }
+
+        if !self.title.is_empty() && !self.has_border() {
+            bail!("title requires a border");
+        }
+
+        if self.zindex < 1 {
+            bail!("zindex must be positive");
+        }
+
+        if self.max_buffers < 1 {
+            bail!("max_buffers must be positive");
+        }
+
+        let ids = self.groups().len();
+
+        if ids > 10usize.pow(ID_WIDTH as u32) {
+            bail!("too many layer colors: {}", ids - 1);
+        }
+
+        for layer in &self.layers {
+            for color in &layer.colors {
+                color.validate()?;
+            }
+        }
+
+        self.colors.validate()
+    }
+
+    /// highlight group and foreground color of every highlight id.
+    /// id 0 is an empty cell, and the levels of each layer follow in order.
+    fn groups(&self) -> Vec<(String, Option<&Color>)> {
+        let mut result = vec![("picomap_none".to_string(), None)];
+
+        for layer in &self.layers {
+            for (i, color) in layer.colors.iter().enumerate() {
+                result.push((format!("picomap_{}{:>02}", layer.name, i + 1), Some(color)));
+            }
+        }
+
+        result
+    }
+
+    /// highlight id of level 0 of each layer, so that a level maps to `offset + level`
+    pub fn offsets(&self) -> Vec<u64> {
+        self.layers
+            .iter()
+            .scan(0, |offset, layer| {
+                let result = *offset;
+                *offset += layer.colors.len() as u64;
+                Some(result)
+            })
+            .collect()
+    }
+
+    /// whether the layer named `name` is drawn
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.layers
+            .iter()
+            .any(|layer| layer.name == name && layer.enabled)
+    }
+
+    /// whether any layer of the rules of `prefix`, like `sign1` for `sign`, is enabled
+    pub fn is_rule_enabled(&self, prefix: &str) -> bool {
+        self.layers
+            .iter()
+            .any(|layer| layer.enabled && rule_index(&layer.name, prefix).is_some())
+    }
+
+    /// highlight commands for every map group, one per highlight id and modifier
+    pub fn to_commands(&self) -> Vec<String> {
+        let cterm = |color: Option<&Color>| match color {
+            Some(color) => color.cterm.to_string(),
+            None => "NONE".to_string(),
+        };
+        let gui = |color: Option<&Color>| match color {
+            Some(color) => color.gui.to_string(),
+            None => "NONE".to_string(),
+        };
+
+        let mut result = Vec::new();
+
+        for (group, fg) in self.groups() {
+            for (_, suffix, bg) in self.colors.modifiers().iter() {
+                result.push(format!(
+                    "highlight {}{} ctermbg={} ctermfg={} guibg={} guifg={}",
+                    group,
+                    suffix,
+                    cterm(*bg),
+                    cterm(fg),
+                    gui(*bg),
+                    gui(fg),
+                ));
+            }
+        }
+
+        result
+    }
+
+    /// group and pattern of every match coloring a map cell.
+    /// a cell is matched by its column, the hidden highlight id of the column and the modifier.
+    pub fn to_matches(&self) -> Vec<(String, String)> {
+        let width = self.width as usize;
+        let mut columns = vec![None];
+
+        for layer in &self.layers {
+            for _ in &layer.colors {
+                columns.push(if layer.enabled {
+                    Some(layer.column as usize)
+                } else {
+                    None
+                });
+            }
+        }
+
+        let mut result = Vec::new();
+
+        for (id, (group, _)) in self.groups().into_iter().enumerate() {
+            for k in 0..width {
+                // id 0 appears in every column
+                if id > 0 && columns[id] != Some(k) {
+                    continue;
+                }
+
+                for (modifier, suffix, _) in self.colors.modifiers().iter() {
+                    result.push((
+                        format!("{}{}", group, suffix),
+                        format!(
+                            r"^.\{{{}}}\zs[▖▘▌ ]\ze.\{{{}}}\d\{{{}}}{:>0w$}\d\{{{}}}{}$",
+                            k,
+                            width - 1 - k,
+                            ID_WIDTH * k,
+                            id,
+                            ID_WIDTH * (width - 1 - k),
+                            modifier,
+                            w = ID_WIDTH,
+                        ),
+                    ));
+                }
+            }
+        }
+
+        result
+    }
+
+    fn has_border(&self) -> bool {
+        self.border != "none"
+    }
+
+    /// whether the map should be shown for a window of `info` showing a buffer
+    /// of `filetype` and `buftype` with `lines` lines.
+    /// an empty include list allows everything that is not excluded.
+    pub fn allows(&self, filetype: &str, buftype: &str, info: &WinInfo, lines: u64) -> bool {
+        let matches = |include: &[String], exclude: &[String], value: &str| {
+            (include.is_empty() || include.iter().any(|v| v == value))
+                && !exclude.iter().any(|v| v == value)
+        };
+
+        matches(&self.include_filetypes, &self.exclude_filetypes, filetype)
+            && matches(&self.include_buftypes, &self.exclude_buftypes, buftype)
+            && info.height >= self.min_height
+            && info.width >= self.min_width
+            && lines >= self.min_lines
+    }
+
+    /// text area columns covered by the map, when placed on `side` of `info`
+    fn columns(&self, info: &WinInfo, side: Side) -> (u64, u64) {
+        let border = if self.has_border() { 1 } else { 0 };
+        let width = self.width + border * 2;
+
+        match side {
+            Side::Right => {
+                let right = info.width.saturating_sub(self.offset + info.textoff);
+
+                (right.saturating_sub(width), right)
+            }
+            Side::Left => (self.offset, self.offset + width),
+        }
+    }
+
+    /// whether the map placed on `side` covers the cursor or any visible text
+    pub fn overlaps(&self, info: &WinInfo, extent: &TextExtent, side: Side) -> bool {
+        let (left, right) = self.columns(info, side);
+
+        if left <= extent.cursor && extent.cursor < right {
+            return true;
+        }
+
+        extent
+            .lines
+            .iter()
+            .any(|(start, end)| *start < right && left < *end)
+    }
+
+    /// float window config placing the map beside the text area of `win`
+    pub fn to_win_config(&self, win: &Value, info: &WinInfo, side: Side) -> Vec<(Value, Value)> {
+        let border = if self.has_border() { 1 } else { 0 };
+        let height = max(info.height.saturating_sub(border * 2), 1);
+
+        let (left, top) = match self.relative {
+            Relative::Editor => (info.wincol - 1, info.winrow - 1 + info.winbar),
+            Relative::Win => (0, 0),
+        };
+
+        let (anchor, col) = match side {
+            Side::Right => ("NE", (left + info.width).saturating_sub(self.offset)),
+            Side::Left => ("NW", left + info.textoff + self.offset),
+        };
+
+        let mut config = vec![
+            (
+                Value::from("relative"),
+                Value::from(match self.relative {
+                    Relative::Editor => "editor",
+                    Relative::Win => "win",
+                }),
+            ),
+            (Value::from("anchor"), Value::from(anchor)),
+            (Value::from("width"), Value::from(self.width)),
+            (Value::from("focusable"), Value::from(false)),
+            (Value::from("style"), Value::from("minimal")),
+            (Value::from("height"), Value::from(height)),
+            (Value::from("col"), Value::from(col)),
+            (Value::from("row"), Value::from(top)),
+            (Value::from("zindex"), Value::from(self.zindex)),
+        ];
+
+        if self.relative == Relative::Win {
+            config.push((Value::from("win"), win.clone()));
+        }
+
+        if self.has_border() {
+            config.push((Value::from("border"), Value::from(&self.border[..])));
+        }
+
+        if !self.title.is_empty() {
+            config.push((Value::from("title"), Value::from(&self.title[..])));
+        }
+
+        config
+    }
+
+    /// floating window config of the hover text shown beside the map row `row` of `win`
+    pub fn to_hover_config(
+        &self,
+        win: &Value,
+        row: u64,
+        width: u64,
+        height: u64,
+        side: Side,
+    ) -> Vec<(Value, Value)> {
+        let border = if self.has_border() { 1 } else { 0 };
+
+        // open towards the text, away from the window edge
+        let (anchor, col) = match side {
+            Side::Right => ("NE", -border),
+            Side::Left => ("NW", self.width as i64 + border),
+        };
+
+        vec![
+            (Value::from("relative"), Value::from("win")),
+            (Value::from("win"), win.clone()),
+            (Value::from("anchor"), Value::from(anchor)),
+            (Value::from("width"), Value::from(max(width, 1))),
+            (Value::from("focusable"), Value::from(false)),
+            (Value::from("style"), Value::from("minimal")),
+            (Value::from("height"), Value::from(max(height, 1))),
+            (Value::from("col"), Value::from(col)),
+            (Value::from("row"), Value::from(row)),
+            (Value::from("zindex"), Value::from(self.zindex + 1)),
+        ]
+    }
+}
+
+impl TryFrom<&Value> for Config {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &Value) -> Result<Self> {
+        let mut config = Config::default();
+
+        config.update(value)?;
+
+        Ok(config)
+    }
+}
+
+fn fields(value: &Value) -> Result<&Vec<(Value, Value)>> {
+    value.as_map().with_context(|| "expected a dictionary")
+}
+
+fn to_u64(value: &Value, key: &str) -> Result<u64> {
+    value
+        .as_u64()
+        .with_context(|| format!("{} must be a positive number", key))
+}
+
+fn to_string(value: &Value, key: &str) -> Result<String> {
+    Ok(value
+        .as_str()
+        .with_context(|| format!("{} must be a string", key))?
+        .to_string())
+}
+
+fn to_strings(value: &Value, key: &str) -> Result<Vec<String>> {
+    value
+        .as_array()
+        .with_context(|| format!("{} must be a list", key))?
+        .iter()
+        .map(|value| to_string(value, key))
+        .collect()
+}
+
+fn to_owned(values: &[&str]) -> Vec<String> {
+    values.iter().map(|value| value.to_string()).collect()
+}
+
+/// update `color` by the `cterm`, `gui` or `color` key of a rule,
+/// returning false for the other keys
+fn update_rule_color(color: &mut Color, key: &str, value: &Value) -> Result<bool> {
+    match key {
+        "cterm" => color.cterm = to_u64(value, "cterm")?,
+        "gui" => color.gui = to_string(value, "gui")?,
+        "color" => color.update(value).context("invalid color")?,
+        _ => return Ok(false),
+    }
+
+    Ok(true)
+}
+
+/// a list of `{'keyword': 'TODO', 'cterm': 179, 'gui': '#e2a478'}`
+fn to_annotations(value: &Value) -> Result<Vec<(String, Color)>> {
+    let values = value.as_array().with_context(|| "expected a list")?;
+
+    values
+        .iter()
+        .map(|value| {
+            let mut keyword = None;
+            let mut color = Color::new(0, "NONE");
+
+            for (key, value) in fields(value)? {
+                match key.as_str().with_context(|| "invalid annotation key")? {
+                    "keyword" => keyword = Some(to_string(value, "keyword")?),
+                    key => {
+                        if !update_rule_color(&mut color, key, value)? {
+                            bail!("unknown annotation key: {}", key);
+                        }
+                    }
+                }
+            }
+
+            match keyword {
+                Some(keyword) if !keyword.is_empty() => Ok((keyword, color)),
+                _ => bail!("annotation requires a keyword"),
+            }
+        })
+        .collect()
+}
+
+/// index of the rule drawn by a layer named like `sign1`, `sign2`, ... for the `prefix` `sign`
+pub fn rule_index(name: &str, prefix: &str) -> Option<usize> {
+    name.strip_prefix(prefix)?
+        .parse::<usize>()
+        .ok()?
+        .checked_sub(1)
+}
+
+/// replace the layers of the rules of `prefix` with a layer for each of `rules`,
+/// drawn over the other layers but folds, and return the patterns of the rules
+fn replace_rule_layers<T: Clone>(
+    layers: &mut Vec<LayerConfig>,
+    prefix: &str,
+    rules: &[(T, u64, Color)],
+) -> Vec<T> {
+    layers.retain(|layer| rule_index(&layer.name, prefix).is_none());
+
+    let index = layers
+        .iter()
+        .position(|layer| layer.name == "fold")
+        .unwrap_or(layers.len());
+
+    layers.splice(
+        index..index,
+        rules.iter().enumerate().map(|(i, (_, column, color))| {
+            LayerConfig::new(
+                &format!("{}{}", prefix, i + 1),
+                *column,
+                vec![color.clone()],
+            )
+        }),
+    );
+
+    rules
+        .iter()
+        .map(|(patterns, _, _)| patterns.clone())
+        .collect()
+}
+
+/// rules given as a list of `{key, key, column, cterm, gui}` with two pattern `keys`,
+/// either of which is required
+fn to_rules(value: &Value, kind: &str, keys: [&str; 2]) -> Result<Vec<([String; 2], u64, Color)>> {
+    let values = value.as_array().with_context(|| "expected a list")?;
+
+    values
+        .iter()
+        .map(|value| {
+            let mut patterns = [String::new(), String::new()];
+            let mut column = 1;
+            let mut color = Color::new(0, "NONE");
+
+            for (key, value) in fields(value)? {
+                match key
+                    .as_str()
+                    .with_context(|| format!("invalid {} key", kind))?
+                {
+                    key if key == keys[0] => patterns[0] = to_string(value, key)?,
+                    key if key == keys[1] => patterns[1] = to_string(value, key)?,
+                    "column" => column = to_u64(value, "column")?,
+                    key => {
+                        if !update_rule_color(&mut color, key, value)? {
+                            bail!("unknown {} key: {}", kind, key);
+                        }
+                    }
+                }
+            }
+
+            if patterns.iter().all(|pattern| pattern.is_empty()) {
+                bail!("{} requires {} or {}", kind, keys[0], keys[1]);
+            }
+
+            Ok((patterns, column, color))
+        })
+        .collect()
+}
+
+/// regex rules given as a list of `{pattern, filetypes, column, color}`,
+/// where `cterm` and `gui` may be given instead of `color`
+fn to_regex_rules(value: &Value) -> Result<Vec<(RegexRule, u64, Color)>> {
+    let values = value.as_array().with_context(|| "expected a list")?;
+
+    values
+        .iter()
+        .map(|value| {
+            let mut pattern = None;
+            let mut filetypes = vec![];
+            let mut column = 1;
+            let mut color = Color::new(0, "NONE");
+
+            for (key, value) in fields(value)? {
+                match key.as_str().with_context(|| "invalid rule key")? {
+                    "pattern" => pattern = Some(to_string(value, "pattern")?),
+                    "filetypes" => filetypes = to_strings(value, "filetypes")?,
+                    "column" => column = to_u64(value, "column")?,
+                    key => {
+                        if !update_rule_color(&mut color, key, value)? {
+                            bail!("unknown rule key: {}", key);
+                        }
+                    }
+                }
+            }
+
+            let pattern = match pattern {
+                Some(pattern) if !pattern.is_empty() => pattern,
+                _ => bail!("rule requires a pattern"),
+            };
+
+            let regex = Regex::new(&pattern)
+                .with_context(|| format!("invalid rule pattern: {}", pattern))?;
+
+            Ok((RegexRule { regex, filetypes }, column, color))
+        })
+        .collect()
+}
+
+fn update_levels(colors: &mut [Color], value: &Value) -> Result<()> {
+    let values = value.as_array().with_context(|| "expected a list")?;
+
+    if values.len() != colors.len() {
+        bail!("expected {} colors, got {}", colors.len(), values.len());
+    }
+
+    for (color, value) in colors.iter_mut().zip(values) {
+        color.update(value)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(fields: Vec<(&str, Value)>) -> Value {
+        Value::Map(
+            fields
+                .into_iter()
+                .map(|(key, value)| (Value::from(key), value))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_config_defaults() {
+        let config = Config::try_from(&map(vec![])).unwrap();
+
+        assert_eq!(config, Config::default());
+    }
+
+    #[test]
+    fn test_config_update() {
+        let mut config = Config::default();
+
+        config
+            .update(&map(vec![
+                ("winblend", Value::from(50)),
+                ("side", Value::from("left")),
+                ("lines", Value::from("folds")),
+                (
+                    "colors",
+                    map(vec![("cursor", map(vec![("gui", Value::from("#000000"))]))]),
+                ),
+            ]))
+            .unwrap();
+
+        assert_eq!(config.winblend, 50);
+        assert_eq!(config.width, 2);
+        assert_eq!(config.side, Side::Left);
+        assert_eq!(config.lines, Lines::Folds);
+        assert_eq!(config.colors.cursor, Color::new(237, "#000000"));
+    }
+
+    #[test]
+    fn test_config_update_invalid() {
+        let mut config = Config::default();
+
+        assert!(config
+            .update(&map(vec![
+                ("winblend", Value::from(10)),
+                ("width", Value::from(9)),
+            ]))
+            .is_err());
+        assert!(config.update(&map(vec![("foo", Value::from(1))])).is_err());
+        assert!(config
+            .update(&map(vec![("title", Value::from("map"))]))
+            .is_err());
+        assert!(config
+            .update(&map(vec![(
+                "colors",
+                map(vec![("view", map(vec![("gui", Value::from("red"))]))]),
+            )]))
+            .is_err());
+
+        assert_eq!(config, Config::default());
+    }
+
+    #[test]
+    fn test_config_update_layers() {
+        let mut config = Config::default();
+
+        config
+            .update(&map(vec![(
+                "layers",
+                map(vec![(
+                    "quickfix",
+                    map(vec![
+                        ("column", Value::from(0)),
+                        ("enabled", Value::from(false)),
+                    ]),
+                )]),
+            )]))
+            .unwrap();
+
+        let layer = config
+            .layers
+            .iter()
+            .find(|layer| layer.name == "quickfix")
+            .unwrap();
+
+        assert_eq!(layer.column, 0);
+        assert!(!layer.enabled);
+
+        assert!(config
+            .update(&map(vec![("layers", map(vec![("foo", map(vec![]))]))]))
+            .is_err());
+    }
+
+    #[test]
+    fn test_config_update_annotations() {
+        let mut config = Config::default();
+
+        config
+            .update(&map(vec![(
+                "annotations",
+                Value::from(vec![map(vec![
+                    ("keyword", Value::from("NOTE")),
+                    ("cterm", Value::from(64)),
+                    ("gui", Value::from("#668e3d")),
+                ])]),
+            )]))
+            .unwrap();
+
+        let layer = config
+            .layers
+            .iter()
+            .find(|layer| layer.name == "annotation")
+            .unwrap();
+
+        assert_eq!(config.annotations, vec!["NOTE".to_string()]);
+        assert_eq!(layer.colors, vec![Color::new(64, "#668e3d")]);
+
+        assert!(config
+            .update(&map(vec![(
+                "annotations",
+                Value::from(vec![map(vec![("cterm", Value::from(64))])]),
+            )]))
+            .is_err());
+    }
+
+    #[test]
+    fn test_config_update_rules() {
+        let mut config = Config::default();
+        let sign = |name: &str, column: u64| {
+            map(vec![
+                ("name", Value::from(name)),
+                ("column", Value::from(column)),
+                ("gui", Value::from("#e27878")),
+            ])
+        };
+
+        config
+            .update(&map(vec![(
+                "signs",
+                Value::from(vec![sign("Dap*", 0), sign("Neotest*", 1)]),
+            )]))
+            .unwrap();
+
+        config
+            .update(&map(vec![(
+                "signs",
+                Value::from(vec![sign("Dap*", 0), sign("GitSigns*", 1)]),
+            )]))
+            .unwrap();
+
+        let names = config
+            .layers
+            .iter()
+            .map(|layer| &layer.name[..])
+            .rev()
+            .take(3)
+            .collect::<Vec<_>>();
+
+        assert_eq!(names, vec!["fold", "sign2", "sign1"]);
+        assert_eq!(config.signs[1].name, "GitSigns*");
+        assert_eq!(config.layers[config.layers.len() - 3].column, 0);
+        assert_eq!(rule_index("sign2", "sign"), Some(1));
+        assert_eq!(rule_index("sign0", "sign"), None);
+        assert!(config.is_rule_enabled("sign"));
+        assert!(!config.is_rule_enabled("extmark"));
+
+        config
+            .update(&map(vec![(
+                "extmarks",
+                Value::from(vec![map(vec![("namespace", Value::from("dap*"))])]),
+            )]))
+            .unwrap();
+
+        assert_eq!(config.extmarks[0].namespace, "dap*");
+        assert_eq!(config.layers[config.layers.len() - 2].name, "extmark1");
+        assert!(config.is_rule_enabled("extmark"));
+
+        config
+            .update(&map(vec![(
+                "rules",
+                Value::from(vec![map(vec![
+                    ("pattern", Value::from(r"\bunsafe\b")),
+                    ("filetypes", Value::from(vec![Value::from("rust")])),
+                    (
+                        "color",
+                        map(vec![
+                            ("cterm", Value::from(203)),
+                            ("gui", Value::from("#e27878")),
+                        ]),
+                    ),
+                ])]),
+            )]))
+            .unwrap();
+
+        assert!(config.rules[0].applies("rust"));
+        assert!(!config.rules[0].applies("c"));
+        assert_eq!(config.layers[config.layers.len() - 2].name, "rule1");
+        assert_eq!(
+            config.layers[config.layers.len() - 2].colors,
+            vec![Color::new(203, "#e27878")]
+        );
+
+        assert!(config
+            .update(&map(vec![(
+                "rules",
+                Value::from(vec![map(vec![("pattern", Value::from("(unsafe"))])]),
+            )]))
+            .is_err());
+
+        assert!(config
+            .update(&map(vec![(
+                "signs",
+                Value::from(vec![map(vec![("column", Value::from(0))])]),
+            )]))
+            .is_err());
+    }
+
+    #[test]
+    fn test_config_offsets() {
+        let config = Config::default();
+        let offsets = config.offsets();
+
+        assert_eq!(offsets.len(), config.layers.len());
+        assert_eq!(offsets[0], 0);
+
+        for (offsets, layer) in offsets.windows(2).zip(&config.layers) {
+            assert_eq!(offsets[1], offsets[0] + layer.colors.len() as u64);
+        }
+    }
+
+    /// number of the colors of the default layers, only of the enabled ones with `enabled`
+    fn count_colors(config: &Config, enabled: bool) -> usize {
+        config
+            .layers
+            .iter()
+            .filter(|layer| layer.enabled || !enabled)
+            .map(|layer| layer.colors.len())
+            .sum()
+    }
+
+    /// first highlight id of the layer `name`
+    fn offset(config: &Config, name: &str) -> u64 {
+        let k = config
+            .layers
+            .iter()
+            .position(|layer| layer.name == name)
+            .unwrap();
+
+        config.offsets()[k]
+    }
+
+    #[test]
+    fn test_config_to_commands() {
+        let config = Config::default();
+        let commands = config.to_commands();
+
+        // one for each modifier of id 0 and every level
+        assert_eq!(commands.len(), 6 * (1 + count_colors(&config, false)));
+        assert_eq!(
+            commands[0],
+            "highlight picomap_none ctermbg=NONE ctermfg=NONE guibg=NONE guifg=NONE"
+        );
+        assert!(commands.contains(
+            &"highlight picomap_change01cursor ctermbg=237 ctermfg=64 guibg=#2a3158 guifg=#668e3d"
+                .to_string()
+        ));
+    }
+
+    #[test]
+    fn test_config_to_matches() {
+        let config = Config::default();
+        let matches = config.to_matches();
+        let patterns = |group: &str| {
+            matches
+                .iter()
+                .filter(|(g, _)| g == group)
+                .map(|(_, pattern)| pattern.clone())
+                .collect::<Vec<_>>()
+        };
+
+        // one for each modifier of id 0 in every column and every level of the enabled layers
+        assert_eq!(
+            matches.len(),
+            6 * (config.width as usize + count_colors(&config, true))
+        );
+        assert_eq!(
+            matches[0],
+            (
+                "picomap_none".to_string(),
+                r"^.\{0}\zs[▖▘▌ ]\ze.\{1}\d\{0}000\d\{3} $".to_string()
+            )
+        );
+        assert_eq!(
+            patterns("picomap_change01cursor"),
+            vec![format!(
+                r"^.\{{0}}\zs[▖▘▌ ]\ze.\{{1}}\d\{{0}}{:>03}\d\{{3}}c$",
+                offset(&config, "change") + 1
+            )]
+        );
+        assert_eq!(
+            patterns("picomap_diag02"),
+            vec![format!(
+                r"^.\{{1}}\zs[▖▘▌ ]\ze.\{{0}}\d\{{3}}{:>03}\d\{{0}} $",
+                offset(&config, "diag") + 2
+            )]
+        );
+        assert!(patterns("picomap_blame01").is_empty());
+    }
+
+    fn get<'a>(config: &'a [(Value, Value)], key: &str) -> Option<&'a Value> {
+        config
+            .iter()
+            .find(|field| field.0.as_str() == Some(key))
+            .map(|field| &field.1)
+    }
+
+    #[test]
+    fn test_config_to_win_config() {
+        let info = WinInfo {
+            winrow: 2,
+            wincol: 11,
+            width: 80,
+            height: 20,
+            textoff: 4,
+            winbar: 1,
+        };
+        let win = Value::from(1000);
+
+        let config = Config::default().to_win_config(&win, &info, Side::Right);
+
+        assert_eq!(get(&config, "anchor"), Some(&Value::from("NE")));
+        assert_eq!(get(&config, "col"), Some(&Value::from(90)));
+        assert_eq!(get(&config, "row"), Some(&Value::from(2)));
+        assert_eq!(get(&config, "height"), Some(&Value::from(20)));
+        assert_eq!(get(&config, "win"), None);
+
+        let mut config = Config::default();
+
+        config
+            .update(&map(vec![
+                ("side", Value::from("left")),
+                ("relative", Value::from("win")),
+                ("offset", Value::from(1)),
+                ("border", Value::from("single")),
+                ("title", Value::from("map")),
+            ]))
+            .unwrap();
+
+        let config = config.to_win_config(&win, &info, config.side);
+
+        assert_eq!(get(&config, "anchor"), Some(&Value::from("NW")));
+        assert_eq!(get(&config, "col"), Some(&Value::from(5)));
+        assert_eq!(get(&config, "row"), Some(&Value::from(0)));
+        assert_eq!(get(&config, "height"), Some(&Value::from(18)));
+        assert_eq!(get(&config, "win"), Some(&win));
+        assert_eq!(get(&config, "border"), Some(&Value::from("single")));
+    }
+
+    #[test]
+    fn test_config_to_hover_config() {
+        let win = Value::from(1000);
+
+        let config = Config::default().to_hover_config(&win, 3, 12, 2, Side::Right);
+
+        assert_eq!(get(&config, "anchor"), Some(&Value::from("NE")));
+        assert_eq!(get(&config, "col"), Some(&Value::from(0)));
+        assert_eq!(get(&config, "row"), Some(&Value::from(3)));
+        assert_eq!(get(&config, "width"), Some(&Value::from(12)));
+        assert_eq!(get(&config, "win"), Some(&win));
+
+        let mut config = Config::default();
+
+        config
+            .update(&map(vec![("border", Value::from("single"))]))
+            .unwrap();
+
+        let config = config.to_hover_config(&win, 3, 12, 2, Side::Left);
+
+        assert_eq!(get(&config, "anchor"), Some(&Value::from("NW")));
+        assert_eq!(get(&config, "col"), Some(&Value::from(3)));
+    }
+
+    #[test]
+    fn test_config_overlaps() {
+        let info = WinInfo {
+            width: 24,
+            textoff: 4,
+            ..WinInfo::default()
+        };
+        let config = Config::default();
+
+        assert!(!config.overlaps(
+            &info,
+            &TextExtent {
+                cursor: 0,
+                lines: vec![(0, 10), (2, 18)],
+            },
+            Side::Right
+        ));
+        assert!(config.overlaps(
+            &info,
+            &TextExtent {
+                cursor: 0,
+                lines: vec![(0, 10), (2, 19)],
+            },
+            Side::Right
+        ));
+        assert!(config.overlaps(
+            &info,
+            &TextExtent {
+                cursor: 18,
+                lines: vec![],
+            },
+            Side::Right
+        ));
+        assert!(config.overlaps(
+            &info,
+            &TextExtent {
+                cursor: 0,
+                lines: vec![(4, 10)],
+            },
+            Side::Left
+        ));
+        assert!(!config.overlaps(
+            &info,
+            &TextExtent {
+                cursor: 4,
+                lines: vec![(2, 10)],
+            },
+            Side::Left
+        ));
+    }
+
+    #[test]
+    fn test_config_allows() {
+        let info = WinInfo {
+            width: 80,
+            height: 20,
+            ..WinInfo::default()
+        };
+        let mut config = Config::default();
+
+        assert!(config.allows("rust", "", &info, 100));
+        assert!(!config.allows("help", "help", &info, 100));
+        assert!(!config.allows("", "terminal", &info, 100));
+        assert!(!config.allows("rust", "", &info, 0));
+        assert!(!config.allows("rust", "", &WinInfo { height: 3, ..info }, 100));
+
+        config
+            .update(&map(vec![(
+                "include_filetypes",
+                Value::Array(vec![Value::from("markdown")]),
+            )]))
+            .unwrap();
+
+        assert!(!config.allows("rust", "", &info, 100));
+        assert!(config.allows("markdown", "", &info, 100));
+    }
+}
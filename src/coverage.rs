@@ -0,0 +1,238 @@
+use crate::watch::same_file;
+use crate::xml;
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineCoverage {
+    Covered,
+    Partial,
+    Uncovered,
+}
+
+/// hits of a line and whether one of its branches was never taken
+#[derive(Debug, Default, Clone, Copy)]
+struct LineHits {
+    hits: u64,
+    missed_branch: bool,
+}
+
+impl LineHits {
+    fn to_coverage(self) -> LineCoverage {
+        if self.hits == 0 {
+            LineCoverage::Uncovered
+        } else if self.missed_branch {
+            LineCoverage::Partial
+        } else {
+            LineCoverage::Covered
+        }
+    }
+}
+
+/// line coverage of each source file in an lcov or Cobertura report
+#[derive(Debug, Default)]
+pub struct CoverageReport {
+    files: Vec<(String, Vec<(u64, LineCoverage)>)>,
+}
+
+impl CoverageReport {
+    /// parse an lcov tracefile, or a Cobertura XML report when it starts with a tag
+    pub fn parse(text: &str) -> Result<Self> {
+        let files = if text.trim_start().starts_with('<') {
+            parse_cobertura(text)?
+        } else {
+            parse_lcov(text)?
+        };
+
+        Ok(CoverageReport {
+            files: files
+                .into_iter()
+                .map(|(path, lines)| {
+                    let lines = lines
+                        .into_iter()
+                        .map(|(lnum, hits)| (lnum, hits.to_coverage()))
+                        .collect();
+
+                    (path, lines)
+                })
+                .collect(),
+        })
+    }
+
+    /// coverage of the 1-based lines of the file `name`
+    pub fn lines(&self, name: &str) -> &[(u64, LineCoverage)] {
+        self.files
+            .iter()
+            .find(|(path, _)| same_file(name, path))
+            .map(|(_, lines)| &lines[..])
+            .unwrap_or(&[])
+    }
+}
+
+type Files = BTreeMap<String, BTreeMap<u64, LineHits>>;
+
+fn parse_lcov(text: &str) -> Result<Files> {
+    let mut files = Files::new();
+    let mut current = None;
+
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+
+        let parse = |value: Option<&str>| -> Result<u64> {
+            value
+                .and_then(|value| value.trim().parse().ok())
+                .with_context(|| format!("invalid lcov line {}: {}", n + 1, line))
+        };
+
+        if let Some(path) = line.strip_prefix("SF:") {
+            current = Some(path.to_string());
+        } else if let Some(data) = line.strip_prefix("DA:") {
+            let mut fields = data.split(',');
+            let lnum = parse(fields.next())?;
+            let hits = parse(fields.next())?;
+
+            if let Some(path) = &current {
+                let entry = files
+                    .entry(path.clone())
+                    .or_default()
+                    .entry(lnum)
+                    .or_default();
+
+                entry.hits += hits;
+            }
+        } else if let Some(data) = line.strip_prefix("BRDA:") {
+            let fields = data.split(',').collect::<Vec<_>>();
+            let lnum = parse(fields.first().copied())?;
+
+            // the count of a branch is `-` when its block was never run
+            let taken = fields.get(3).copied().unwrap_or("-");
+
+            if let Some(path) = &current {
+                let entry = files
+                    .entry(path.clone())
+                    .or_default()
+                    .entry(lnum)
+                    .or_default();
+
+                entry.missed_branch |= taken == "-" || taken == "0";
+            }
+        } else if line == "end_of_record" {
+            current = None;
+        }
+    }
+
+    Ok(files)
+}
+
+fn parse_cobertura(text: &str) -> Result<Files> {
+    let mut files = Files::new();
+    let mut current = None;
+
+    for tag in xml::tags(text).into_iter().filter(|tag| !tag.end) {
+        match tag.name {
+            "class" => current = tag.attr("filename").map(|path| path.to_string()),
+            "line" => {
+                let path = match &current {
+                    Some(path) => path,
+                    None => continue,
+                };
+
+                let lnum = tag
+                    .attr("number")
+                    .and_then(|value| value.parse::<u64>().ok())
+                    .context("invalid line number")?;
+                let hits = tag
+                    .attr("hits")
+                    .and_then(|value| value.parse::<u64>().ok())
+                    .context("invalid line hits")?;
+
+                // e.g. condition-coverage="50% (1/2)"
+                let all_taken = matches!(
+                    tag.attr("condition-coverage"),
+                    Some(value) if value.starts_with("100%")
+                );
+                let missed_branch = tag.attr("branch") == Some("true") && !all_taken;
+
+                let entry = files
+                    .entry(path.clone())
+                    .or_default()
+                    .entry(lnum)
+                    .or_default();
+
+                // lines of a class are listed again under its methods
+                entry.hits = entry.hits.max(hits);
+                entry.missed_branch |= missed_branch;
+            }
+            _ => {}
+        }
+    }
+
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coverage_report_parse_lcov() {
+        let report = CoverageReport::parse(
+            "TN:
+SF:src/main.rs
+DA:1,1
+DA:2,0
+DA:3,4
+BRDA:3,0,0,1
+BRDA:3,0,1,-
+end_of_record
+SF:src/lib.rs
+DA:1,0
+end_of_record
+",
+        )
+        .unwrap();
+
+        assert_eq!(
+            report.lines("/home/foo/project/src/main.rs"),
+            &[
+                (1, LineCoverage::Covered),
+                (2, LineCoverage::Uncovered),
+                (3, LineCoverage::Partial),
+            ]
+        );
+        assert!(report.lines("/home/foo/project/src/server.rs").is_empty());
+
+        assert!(CoverageReport::parse("SF:src/main.rs\nDA:foo,1\n").is_err());
+    }
+
+    #[test]
+    fn test_coverage_report_parse_cobertura() {
+        let report = CoverageReport::parse(
+            r#"<?xml version="1.0" ?>
+<coverage>
+  <packages><package name="foo"><classes>
+    <class name="main" filename="src/main.rs">
+      <methods><method name="main"><lines>
+        <line number="2" hits="3"/>
+      </lines></method></methods>
+      <lines>
+        <line number="1" hits="1"/>
+        <line number="2" hits="3" branch="true" condition-coverage="50% (1/2)"/>
+        <line number="3" hits="0"/>
+      </lines>
+    </class>
+  </classes></package></packages>
+</coverage>"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            report.lines("/home/foo/project/src/main.rs"),
+            &[
+                (1, LineCoverage::Covered),
+                (2, LineCoverage::Partial),
+                (3, LineCoverage::Uncovered),
+            ]
+        );
+    }
+}
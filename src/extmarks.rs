@@ -0,0 +1,70 @@
+use crate::message::Extmark;
+use crate::signs::matches_pattern;
+
+/// extmarks of the current buffer with a highlight group in the namespaces matching any of
+/// the patterns given as the argument, as a list of `{start, end, hl_group, namespace}`
+/// with 0-based rows
+pub const EXTMARKS: &str = r#"
+local patterns = ...
+
+local function matches(name)
+  for _, pattern in ipairs(patterns) do
+    if pattern == '' or pattern == name then
+      return true
+    end
+
+    if pattern:sub(-1) == '*' and vim.startswith(name, pattern:sub(1, -2)) then
+      return true
+    end
+  end
+
+  return false
+end
+
+local extmarks = {}
+
+for name, id in pairs(vim.api.nvim_get_namespaces()) do
+  if matches(name) then
+    for _, mark in ipairs(vim.api.nvim_buf_get_extmarks(0, id, 0, -1, { details = true })) do
+      local details = mark[4] or {}
+      local group = details.hl_group or details.sign_hl_group
+
+      if type(group) == 'table' then
+        group = group[1]
+      end
+
+      if group then
+        local last = details.end_row or mark[2]
+
+        -- a range ending at the first column does not cover its last row
+        if last > mark[2] and details.end_col == 0 then
+          last = last - 1
+        end
+
+        table.insert(extmarks, {
+          start = mark[2],
+          ['end'] = last,
+          hl_group = group,
+          namespace = name,
+        })
+      end
+    end
+  end
+end
+
+return extmarks
+"#;
+
+/// extmarks drawn by an extmark layer, matched by their namespace and highlight group
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ExtmarkRule {
+    pub namespace: String,
+    pub hl_group: String,
+}
+
+impl ExtmarkRule {
+    pub fn matches(&self, extmark: &Extmark) -> bool {
+        matches_pattern(&self.namespace, &extmark.namespace)
+            && matches_pattern(&self.hl_group, &extmark.hl_group)
+    }
+}
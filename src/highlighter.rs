@@ -1,10 +1,83 @@
+mod annotation;
+mod blame;
+mod conflict;
+mod coverage;
+mod extmark;
+mod format;
+mod history;
+mod marks;
+mod occurrence;
+mod outline;
+mod profile;
+mod quickfix;
+mod recent;
+mod rule;
+mod sign;
+mod spell;
+
+pub use annotation::*;
+pub use blame::*;
+pub use conflict::*;
+pub use coverage::*;
+pub use extmark::*;
+pub use format::*;
+pub use history::*;
+pub use marks::*;
+pub use occurrence::*;
+pub use outline::*;
+pub use profile::*;
+pub use quickfix::*;
+pub use recent::*;
+pub use rule::*;
+pub use sign::*;
+pub use spell::*;
+
 const LINE_CAPACITY: usize = 500;
 
+const HOUR: u64 = 60 * 60;
+const DAY: u64 = 24 * HOUR;
+const MONTH: u64 = 30 * DAY;
+const YEAR: u64 = 365 * DAY;
+
 pub type Highlight = u64;
 pub type Highlights = Vec<Highlight>;
 
 pub trait Highlighter {
     fn highlight(&self) -> Highlights;
+
+    /// texts describing line `i`, shown when its map row is hovered
+    fn describe(&self, _i: usize) -> Vec<String> {
+        vec![]
+    }
+}
+
+/// how long ago something happened `seconds` ago, like `3 days ago`
+fn to_age(seconds: u64) -> String {
+    let (n, unit) = match seconds {
+        s if s >= YEAR => (s / YEAR, "year"),
+        s if s >= MONTH => (s / MONTH, "month"),
+        s if s >= DAY => (s / DAY, "day"),
+        s if s >= HOUR => (s / HOUR, "hour"),
+        s if s >= 60 => (s / 60, "minute"),
+        _ => return "just now".to_string(),
+    };
+
+    format!("{} {}{} ago", n, unit, if n == 1 { "" } else { "s" })
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// whether `word` appears in `line` without other word characters around it
+fn contains_word(line: &str, word: &str) -> bool {
+    line.match_indices(word).any(|(start, _)| {
+        let before = line[..start].chars().next_back();
+        let after = line[start + word.len()..].chars().next();
+
+        !matches!(before, Some(c) if is_word_char(c))
+            && !matches!(after, Some(c) if is_word_char(c))
+    })
 }
 
 #[derive(Debug)]
@@ -30,12 +103,14 @@ pub struct Diagnostic {
 #[derive(Debug)]
 pub struct DiagnosticsHighlighter {
     values: Vec<DiagnosticLevel>,
+    texts: Vec<Vec<String>>,
 }
 
 impl Default for DiagnosticsHighlighter {
     fn default() -> Self {
         Self {
             values: Vec::with_capacity(LINE_CAPACITY),
+            texts: Vec::with_capacity(LINE_CAPACITY),
         }
     }
 }
@@ -44,6 +119,8 @@ impl DiagnosticsHighlighter {
     pub fn sync(&mut self, len: usize, diags: Vec<Diagnostic>) {
         self.values.clear();
         self.values.resize_with(len, Default::default);
+        self.texts.clear();
+        self.texts.resize_with(len, Default::default);
 
         for diag in diags {
             if diag.i >= len {
@@ -51,6 +128,7 @@ impl DiagnosticsHighlighter {
                 continue;
             }
             self.values[diag.i] = diag.level;
+            self.texts[diag.i].push(diag.text);
         }
     }
 }
@@ -66,6 +144,10 @@ impl Highlighter for DiagnosticsHighlighter {
             })
             .collect()
     }
+
+    fn describe(&self, i: usize) -> Vec<String> {
+        self.texts.get(i).cloned().unwrap_or_default()
+    }
 }
 
 #[derive(Debug, Default)]
@@ -142,6 +224,7 @@ mod tests {
         );
 
         assert_eq!(highlighter.highlight(), vec![0, 2, 1]);
+        assert_eq!(highlighter.describe(1), vec!["foo".to_string()]);
     }
 
     #[test]
@@ -0,0 +1,85 @@
+use super::*;
+use std::cmp::min;
+
+#[derive(Debug)]
+pub struct AnnotationHighlighter {
+    values: Vec<Highlight>,
+}
+
+impl Default for AnnotationHighlighter {
+    fn default() -> Self {
+        Self {
+            values: Vec::with_capacity(LINE_CAPACITY),
+        }
+    }
+}
+
+impl AnnotationHighlighter {
+    /// scan every line for `keywords`, the level of a keyword is its position in the list
+    pub fn sync(&mut self, lines: &[String], keywords: &[String]) {
+        self.values.clear();
+        self.values
+            .extend(lines.iter().map(|line| level(line, keywords)));
+    }
+
+    /// rescan the lines from `first` replacing the old lines until `last`
+    pub fn update(&mut self, first: usize, last: usize, lines: &[String], keywords: &[String]) {
+        let len = self.values.len();
+        let first = min(first, len);
+        let last = min(last, len).max(first);
+
+        self.values
+            .splice(first..last, lines.iter().map(|line| level(line, keywords)));
+    }
+}
+
+impl Highlighter for AnnotationHighlighter {
+    fn highlight(&self) -> Highlights {
+        self.values.clone()
+    }
+}
+
+fn level(line: &str, keywords: &[String]) -> Highlight {
+    keywords
+        .iter()
+        .enumerate()
+        .filter(|(_, keyword)| contains_word(line, keyword))
+        .map(|(i, _)| i as Highlight + 1)
+        .max()
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_annotation_highlighter_update() {
+        let mut highlighter = AnnotationHighlighter::default();
+        let keywords = vec!["TODO".to_string(), "FIXME".to_string()];
+
+        highlighter.sync(
+            &[
+                "// TODO: foo".to_string(),
+                "fn main() {}".to_string(),
+                "// FIXME(bar) TODO".to_string(),
+            ],
+            &keywords,
+        );
+
+        assert_eq!(highlighter.highlight(), vec![1, 0, 2]);
+
+        highlighter.update(
+            1,
+            2,
+            &["// FIXME".to_string(), "// TODOS".to_string()],
+            &keywords,
+        );
+
+        assert_eq!(highlighter.highlight(), vec![1, 2, 0, 2]);
+
+        highlighter.update(0, 2, &[], &keywords);
+
+        assert_eq!(highlighter.highlight(), vec![0, 2]);
+    }
+}
@@ -0,0 +1,117 @@
+use super::*;
+
+/// ages from which a line is a level colder
+const AGES: [u64; 5] = [DAY, 7 * DAY, MONTH, 3 * MONTH, YEAR];
+
+#[derive(Debug, Default, Clone)]
+pub struct BlameItem {
+    pub i: usize,
+    pub author: String,
+    /// unix time the line was last changed
+    pub time: u64,
+}
+
+#[derive(Debug)]
+pub struct BlameHighlighter {
+    values: Vec<Option<BlameItem>>,
+    levels: u64,
+    now: u64,
+}
+
+impl Default for BlameHighlighter {
+    fn default() -> Self {
+        Self {
+            values: Vec::with_capacity(LINE_CAPACITY),
+            levels: 1,
+            now: 0,
+        }
+    }
+}
+
+impl BlameHighlighter {
+    /// grade the age of each line at the unix time `now` into `levels` levels,
+    /// the most recent lines having the highest level
+    pub fn sync(&mut self, len: usize, items: Vec<BlameItem>, now: u64, levels: u64) {
+        self.values.clear();
+        self.values.resize(len, None);
+        self.levels = levels;
+        self.now = now;
+
+        for item in items {
+            if item.i >= len {
+                continue;
+            }
+
+            let i = item.i;
+            self.values[i] = Some(item);
+        }
+    }
+}
+
+impl Highlighter for BlameHighlighter {
+    fn highlight(&self) -> Highlights {
+        self.values
+            .iter()
+            .map(|val| match val {
+                Some(item) => {
+                    let age = self.now.saturating_sub(item.time);
+                    let older = AGES.iter().filter(|limit| age >= **limit).count() as Highlight;
+
+                    self.levels.saturating_sub(older).max(1)
+                }
+                None => 0,
+            })
+            .collect()
+    }
+
+    fn describe(&self, i: usize) -> Vec<String> {
+        match self.values.get(i) {
+            Some(Some(item)) => vec![format!(
+                "blame: {}, {}",
+                item.author,
+                to_age(self.now.saturating_sub(item.time))
+            )],
+            _ => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blame_highlighter_highlight() {
+        let mut highlighter = BlameHighlighter::default();
+        let now = 2 * YEAR;
+        let item = |i, time| BlameItem {
+            i,
+            author: "Alice".to_string(),
+            time,
+        };
+
+        highlighter.sync(
+            5,
+            vec![
+                item(0, now - 10),
+                item(1, now - 2 * DAY),
+                item(2, now - 2 * MONTH),
+                item(3, 0),
+                item(9, now),
+            ],
+            now,
+            4,
+        );
+
+        assert_eq!(highlighter.highlight(), vec![4, 3, 1, 1, 0]);
+        assert_eq!(
+            highlighter.describe(1),
+            vec!["blame: Alice, 2 days ago".to_string()]
+        );
+        assert_eq!(
+            highlighter.describe(2),
+            vec!["blame: Alice, 2 months ago".to_string()]
+        );
+        assert_eq!(highlighter.describe(4), Vec::<String>::new());
+    }
+}
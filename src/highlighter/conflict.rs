@@ -0,0 +1,319 @@
+use super::*;
+use std::cmp::{max, min};
+
+const OPEN: &str = "<<<<<<<";
+const CLOSE: &str = ">>>>>>>";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConflictPart {
+    None,
+    Marker,
+    Ours,
+    Base,
+    Theirs,
+}
+
+impl Default for ConflictPart {
+    fn default() -> Self {
+        Self::None
+    }
+}
+
+#[derive(Debug)]
+pub struct ConflictHighlighter {
+    values: Vec<ConflictPart>,
+    /// first and last lines of each conflict block
+    blocks: Vec<(usize, usize)>,
+    /// lines of the `<<<<<<<` markers, which may be left unclosed
+    opens: Vec<usize>,
+    /// lines of the `>>>>>>>` markers
+    closes: Vec<usize>,
+}
+
+impl Default for ConflictHighlighter {
+    fn default() -> Self {
+        Self {
+            values: Vec::with_capacity(LINE_CAPACITY),
+            blocks: vec![],
+            opens: vec![],
+            closes: vec![],
+        }
+    }
+}
+
+impl ConflictHighlighter {
+    /// find the conflict blocks between `<<<<<<<` and `>>>>>>>` markers.
+    /// a block without its closing marker is left out.
+    pub fn sync(&mut self, lines: &[String]) {
+        self.values.clear();
+        self.values.resize_with(lines.len(), Default::default);
+        self.blocks.clear();
+        self.opens = find_markers(lines, 0, OPEN);
+        self.closes = find_markers(lines, 0, CLOSE);
+
+        self.scan(lines, 0, lines.len());
+    }
+
+    /// find the blocks again after the lines from `first` until `last` were replaced by
+    /// `count` lines, only from the nearest enclosing markers. `lines` are the lines after the change.
+    pub fn update(&mut self, first: usize, last: usize, count: usize, lines: &[String]) {
+        let len = self.values.len();
+        let first = min(first, len);
+        let last = min(last, len).max(first);
+        let end = first + count;
+
+        self.values
+            .splice(first..last, vec![ConflictPart::None; count]);
+
+        shift_markers(&mut self.opens, first, last, end, lines, OPEN);
+        shift_markers(&mut self.closes, first, last, end, lines, CLOSE);
+
+        // an open marker before the change without a close marker between them
+        // may be closed by the changed lines
+        let before = |markers: &[usize]| {
+            markers[..markers.partition_point(|i| *i < first)]
+                .last()
+                .copied()
+        };
+        let mut lo = match (before(&self.opens), before(&self.closes)) {
+            (Some(open), Some(close)) if open > close => open,
+            (Some(open), None) => open,
+            _ => first,
+        };
+
+        // and the blocks opened by the changed lines end at the next close marker
+        let after = |markers: &[usize]| markers.get(markers.partition_point(|i| *i < end)).copied();
+        let mut hi = match (after(&self.opens), after(&self.closes)) {
+            (Some(open), Some(close)) if close < open => close + 1,
+            (None, Some(close)) => close + 1,
+            _ => end,
+        };
+
+        // blocks overlapping the change are found again
+        let mut blocks = Vec::with_capacity(self.blocks.len());
+
+        for (start, stop) in self.blocks.drain(..) {
+            if stop < first {
+                blocks.push((start, stop));
+            } else if start >= last {
+                blocks.push((start - last + end, stop - last + end));
+            } else {
+                // a block ending after the change moves with the lines after it
+                let stop = if stop >= last {
+                    stop - last + end + 1
+                } else {
+                    end
+                };
+
+                lo = min(lo, start);
+                hi = max(hi, stop);
+            }
+        }
+
+        let hi = min(hi, lines.len());
+
+        for value in &mut self.values[lo..hi] {
+            *value = ConflictPart::None;
+        }
+
+        self.blocks = blocks;
+        self.scan(lines, lo, hi);
+        self.blocks.sort_unstable();
+    }
+
+    /// mark the blocks found in the lines from `lo` until `hi`
+    fn scan(&mut self, lines: &[String], lo: usize, hi: usize) {
+        let mut start = None;
+        let mut part = ConflictPart::None;
+        let mut parts = vec![];
+
+        for (i, line) in lines.iter().enumerate().take(hi).skip(lo) {
+            if line.starts_with(OPEN) {
+                start = Some(i);
+                part = ConflictPart::Ours;
+                parts.clear();
+                parts.push(ConflictPart::Marker);
+                continue;
+            }
+
+            let first = match start {
+                Some(first) => first,
+                None => continue,
+            };
+
+            if line.starts_with("|||||||") {
+                part = ConflictPart::Base;
+                parts.push(ConflictPart::Marker);
+            } else if line.starts_with("=======") {
+                part = ConflictPart::Theirs;
+                parts.push(ConflictPart::Marker);
+            } else if line.starts_with(CLOSE) {
+                parts.push(ConflictPart::Marker);
+
+                self.values[first..=i].copy_from_slice(&parts);
+                self.blocks.push((first, i));
+
+                start = None;
+            } else {
+                parts.push(part);
+            }
+        }
+    }
+
+    /// first line of the conflict after line `i`, wrapping around the end of the buffer
+    pub fn next(&self, i: usize) -> Option<usize> {
+        self.blocks
+            .iter()
+            .map(|block| block.0)
+            .find(|start| *start > i)
+            .or_else(|| self.blocks.first().map(|block| block.0))
+    }
+
+    /// first line of the conflict before line `i`, wrapping around the start of the buffer
+    pub fn prev(&self, i: usize) -> Option<usize> {
+        self.blocks
+            .iter()
+            .rev()
+            .map(|block| block.0)
+            .find(|start| *start < i)
+            .or_else(|| self.blocks.last().map(|block| block.0))
+    }
+}
+
+/// lines from `first` starting with `prefix`
+fn find_markers(lines: &[String], first: usize, prefix: &str) -> Vec<usize> {
+    lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| line.starts_with(prefix))
+        .map(|(i, _)| first + i)
+        .collect()
+}
+
+/// move the `markers` after the lines from `first` until `last` replaced by the lines
+/// from `first` until `end` of `lines`, which are searched for `prefix`
+fn shift_markers(
+    markers: &mut Vec<usize>,
+    first: usize,
+    last: usize,
+    end: usize,
+    lines: &[String],
+    prefix: &str,
+) {
+    let at = markers.partition_point(|i| *i < first);
+    let moved = markers
+        .drain(at..)
+        .filter(|i| *i >= last)
+        .map(|i| i - last + end)
+        .collect::<Vec<_>>();
+
+    markers.extend(find_markers(
+        &lines[first..min(end, lines.len())],
+        first,
+        prefix,
+    ));
+    markers.extend(moved);
+}
+
+impl Highlighter for ConflictHighlighter {
+    fn highlight(&self) -> Highlights {
+        self.values
+            .iter()
+            .map(|val| match val {
+                ConflictPart::Marker => 1,
+                ConflictPart::Ours => 2,
+                ConflictPart::Base => 3,
+                ConflictPart::Theirs => 4,
+                _ => 0,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conflict_highlighter_highlight() {
+        let mut highlighter = ConflictHighlighter::default();
+
+        let lines = vec![
+            "fn main() {",
+            "<<<<<<< HEAD",
+            "    foo();",
+            "||||||| base",
+            "    bar();",
+            "=======",
+            "    baz();",
+            ">>>>>>> topic",
+            "<<<<<<< HEAD",
+            "    hoge();",
+            "}",
+        ]
+        .into_iter()
+        .map(|line| line.to_string())
+        .collect::<Vec<_>>();
+
+        highlighter.sync(&lines);
+
+        assert_eq!(
+            highlighter.highlight(),
+            vec![0, 1, 2, 1, 3, 1, 4, 1, 0, 0, 0]
+        );
+        assert_eq!(highlighter.next(1), Some(1));
+        assert_eq!(highlighter.next(0), Some(1));
+        assert_eq!(highlighter.prev(0), Some(1));
+    }
+
+    #[test]
+    fn test_conflict_highlighter_update() {
+        let to_lines = |lines: &[&str]| {
+            lines
+                .iter()
+                .map(|line| line.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        let mut lines = to_lines(&[
+            "<<<<<<< HEAD",
+            "    foo();",
+            "=======",
+            "    bar();",
+            ">>>>>>> topic",
+            "fn main() {",
+            "<<<<<<< HEAD",
+            "    hoge();",
+            "=======",
+            "}",
+        ]);
+
+        let mut highlighter = ConflictHighlighter::default();
+
+        highlighter.sync(&lines);
+
+        // close the second block, remove the first opening marker, then open it again
+        let edits: Vec<(usize, usize, Vec<String>)> = vec![
+            (9, 9, to_lines(&[">>>>>>> topic"])),
+            (0, 1, vec![]),
+            (0, 0, to_lines(&["<<<<<<< HEAD", "    baz();"])),
+            (3, 5, to_lines(&["    qux();"])),
+        ];
+
+        for (first, last, replacement) in edits {
+            let count = replacement.len();
+
+            lines.splice(first..last, replacement);
+            highlighter.update(first, last, count, &lines);
+
+            let mut expected = ConflictHighlighter::default();
+
+            expected.sync(&lines);
+
+            assert_eq!(highlighter.highlight(), expected.highlight());
+            assert_eq!(highlighter.blocks, expected.blocks);
+            assert_eq!(highlighter.opens, expected.opens);
+            assert_eq!(highlighter.closes, expected.closes);
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use super::*;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CoverageKind {
+    None,
+    Partial,
+    Uncovered,
+}
+
+impl Default for CoverageKind {
+    fn default() -> Self {
+        Self::None
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct CoverageItem {
+    pub i: usize,
+    pub kind: CoverageKind,
+}
+
+#[derive(Debug)]
+pub struct CoverageHighlighter {
+    values: Vec<CoverageKind>,
+}
+
+impl Default for CoverageHighlighter {
+    fn default() -> Self {
+        Self {
+            values: Vec::with_capacity(LINE_CAPACITY),
+        }
+    }
+}
+
+impl CoverageHighlighter {
+    pub fn sync(&mut self, len: usize, items: Vec<CoverageItem>) {
+        self.values.clear();
+        self.values.resize_with(len, Default::default);
+
+        for item in items {
+            if item.i >= len {
+                continue;
+            }
+
+            self.values[item.i] = item.kind;
+        }
+    }
+}
+
+impl Highlighter for CoverageHighlighter {
+    fn highlight(&self) -> Highlights {
+        self.values
+            .iter()
+            .map(|val| match val {
+                CoverageKind::Partial => 1,
+                CoverageKind::Uncovered => 2,
+                _ => 0,
+            })
+            .collect()
+    }
+
+    fn describe(&self, i: usize) -> Vec<String> {
+        match self.values.get(i) {
+            Some(CoverageKind::Partial) => vec!["coverage: partially covered".to_string()],
+            Some(CoverageKind::Uncovered) => vec!["coverage: not covered".to_string()],
+            _ => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coverage_highlighter_highlight() {
+        let mut highlighter = CoverageHighlighter::default();
+
+        highlighter.sync(
+            3,
+            vec![
+                CoverageItem {
+                    i: 0,
+                    kind: CoverageKind::Uncovered,
+                },
+                CoverageItem {
+                    i: 2,
+                    kind: CoverageKind::Partial,
+                },
+                CoverageItem {
+                    i: 3,
+                    kind: CoverageKind::Uncovered,
+                },
+            ],
+        );
+
+        assert_eq!(highlighter.highlight(), vec![2, 0, 1]);
+        assert_eq!(
+            highlighter.describe(0),
+            vec!["coverage: not covered".to_string()]
+        );
+    }
+}
@@ -0,0 +1,93 @@
+use super::*;
+
+/// an extmark covering the lines from `start` to `end` inclusive
+#[derive(Debug, Default)]
+pub struct ExtmarkItem {
+    pub start: usize,
+    pub end: usize,
+    pub hl_group: String,
+}
+
+#[derive(Debug)]
+pub struct ExtmarkHighlighter {
+    values: Vec<Vec<String>>,
+}
+
+impl Default for ExtmarkHighlighter {
+    fn default() -> Self {
+        Self {
+            values: Vec::with_capacity(LINE_CAPACITY),
+        }
+    }
+}
+
+impl ExtmarkHighlighter {
+    pub fn sync(&mut self, len: usize, items: Vec<ExtmarkItem>) {
+        self.values.clear();
+        self.values.resize_with(len, Default::default);
+
+        for item in items {
+            if item.start >= len {
+                continue;
+            }
+
+            for i in item.start..=item.end.min(len - 1) {
+                if !self.values[i].contains(&item.hl_group) {
+                    self.values[i].push(item.hl_group.clone());
+                }
+            }
+        }
+    }
+}
+
+impl Highlighter for ExtmarkHighlighter {
+    fn highlight(&self) -> Highlights {
+        self.values
+            .iter()
+            .map(|groups| if groups.is_empty() { 0 } else { 1 })
+            .collect()
+    }
+
+    fn describe(&self, i: usize) -> Vec<String> {
+        self.values
+            .get(i)
+            .map(|groups| {
+                groups
+                    .iter()
+                    .map(|group| format!("extmark: {}", group))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extmark_highlighter_highlight() {
+        let mut highlighter = ExtmarkHighlighter::default();
+        let item = |start, end, hl_group: &str| ExtmarkItem {
+            start,
+            end,
+            hl_group: hl_group.to_string(),
+        };
+
+        highlighter.sync(
+            5,
+            vec![
+                item(1, 2, "DiffAdd"),
+                item(2, 2, "DiffAdd"),
+                item(4, 9, "Search"),
+                item(7, 8, "Search"),
+            ],
+        );
+
+        assert_eq!(highlighter.highlight(), vec![0, 1, 1, 0, 1]);
+        assert_eq!(
+            highlighter.describe(2),
+            vec!["extmark: DiffAdd".to_string()]
+        );
+    }
+}
@@ -0,0 +1,228 @@
+use super::*;
+use std::cmp::min;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Anomaly {
+    /// display width of a line longer than the limit
+    Long(usize),
+    Trailing,
+    MixedIndent,
+    Invisible(char),
+}
+
+impl Anomaly {
+    fn level(&self) -> Highlight {
+        match self {
+            Anomaly::Long(_) => 1,
+            Anomaly::Trailing => 2,
+            Anomaly::MixedIndent => 3,
+            Anomaly::Invisible(_) => 4,
+        }
+    }
+
+    fn describe(&self, limit: usize) -> String {
+        match self {
+            Anomaly::Long(width) => format!("format: {} columns over {}", width, limit),
+            Anomaly::Trailing => "format: trailing whitespace".to_string(),
+            Anomaly::MixedIndent => "format: mixed tab and space indentation".to_string(),
+            Anomaly::Invisible(c) => format!("format: invisible character U+{:04X}", *c as u32),
+        }
+    }
+}
+
+/// zero width characters, bidirectional controls and unusual spaces
+fn is_invisible(c: char) -> bool {
+    matches!(
+        c,
+        '\u{00a0}'
+            | '\u{00ad}'
+            | '\u{061c}'
+            | '\u{180e}'
+            | '\u{2000}'..='\u{200f}'
+            | '\u{2028}'..='\u{202f}'
+            | '\u{205f}'..='\u{206f}'
+            | '\u{feff}'
+    )
+}
+
+/// columns taken by `c`, where east asian wide characters take two
+fn char_width(c: char) -> usize {
+    match c as u32 {
+        0x1100..=0x115f
+        | 0x2e80..=0xa4cf
+        | 0xac00..=0xd7a3
+        | 0xf900..=0xfaff
+        | 0xfe30..=0xfe4f
+        | 0xff00..=0xff60
+        | 0xffe0..=0xffe6
+        | 0x20000..=0x3fffd => 2,
+        _ => 1,
+    }
+}
+
+/// column from which a line is too long, given by `textwidth` or else the first `colorcolumn`,
+/// or 0 when neither is set
+pub fn text_limit(textwidth: usize, colorcolumn: &str) -> usize {
+    if textwidth > 0 {
+        return textwidth;
+    }
+
+    let column = colorcolumn.split(',').next().unwrap_or("");
+
+    // columns relative to textwidth are unset without it
+    if column.starts_with(&['+', '-'][..]) {
+        return 0;
+    }
+
+    column
+        .parse::<usize>()
+        .map_or(0, |column| column.saturating_sub(1))
+}
+
+#[derive(Debug)]
+pub struct FormatHighlighter {
+    values: Vec<Vec<Anomaly>>,
+    limit: usize,
+    tabstop: usize,
+}
+
+impl Default for FormatHighlighter {
+    fn default() -> Self {
+        Self {
+            values: Vec::with_capacity(LINE_CAPACITY),
+            limit: 0,
+            tabstop: 8,
+        }
+    }
+}
+
+impl FormatHighlighter {
+    /// check every line for lines longer than `limit` columns, with tabs of `tabstop` columns
+    pub fn sync(&mut self, lines: &[String], limit: usize, tabstop: usize) {
+        self.limit = limit;
+        self.tabstop = tabstop.max(1);
+        self.values = lines.iter().map(|line| self.anomalies(line)).collect();
+    }
+
+    /// check the lines from `first` replacing the old lines until `last`
+    pub fn update(&mut self, first: usize, last: usize, lines: &[String]) {
+        let len = self.values.len();
+        let first = min(first, len);
+        let last = min(last, len).max(first);
+
+        let values = lines
+            .iter()
+            .map(|line| self.anomalies(line))
+            .collect::<Vec<_>>();
+
+        self.values.splice(first..last, values);
+    }
+
+    /// `limit` and `tabstop` the lines were checked with
+    pub fn options(&self) -> (usize, usize) {
+        (self.limit, self.tabstop)
+    }
+
+    /// whether the lines were checked with other options
+    pub fn outdated(&self, limit: usize, tabstop: usize) -> bool {
+        self.limit != limit || self.tabstop != tabstop.max(1)
+    }
+
+    fn anomalies(&self, line: &str) -> Vec<Anomaly> {
+        let mut result = vec![];
+
+        let indent = &line[..line.len() - line.trim_start_matches(&[' ', '\t'][..]).len()];
+
+        if indent.contains(' ') && indent.contains('\t') {
+            result.push(Anomaly::MixedIndent);
+        }
+
+        if line.ends_with(&[' ', '\t'][..]) {
+            result.push(Anomaly::Trailing);
+        }
+
+        let width = line.chars().fold(0, |width, c| match c {
+            '\t' => (width / self.tabstop + 1) * self.tabstop,
+            c => width + char_width(c),
+        });
+
+        if self.limit > 0 && width > self.limit {
+            result.push(Anomaly::Long(width));
+        }
+
+        if let Some(c) = line.chars().find(|c| is_invisible(*c)) {
+            result.push(Anomaly::Invisible(c));
+        }
+
+        result
+    }
+}
+
+impl Highlighter for FormatHighlighter {
+    fn highlight(&self) -> Highlights {
+        self.values
+            .iter()
+            .map(|anomalies| anomalies.iter().map(Anomaly::level).max().unwrap_or(0))
+            .collect()
+    }
+
+    fn describe(&self, i: usize) -> Vec<String> {
+        self.values
+            .get(i)
+            .map(|anomalies| {
+                anomalies
+                    .iter()
+                    .map(|anomaly| anomaly.describe(self.limit))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_highlighter_highlight() {
+        let mut highlighter = FormatHighlighter::default();
+
+        highlighter.sync(
+            &[
+                "fn main() {".to_string(),
+                "\t  let a = 1; ".to_string(),
+                "\tlet b = \"\u{200b}\";".to_string(),
+                "    // 日本語のコメント".to_string(),
+                "\t// 01234".to_string(),
+            ],
+            12,
+            4,
+        );
+
+        assert_eq!(highlighter.highlight(), vec![0, 3, 4, 1, 0]);
+        assert_eq!(
+            highlighter.describe(1),
+            vec![
+                "format: mixed tab and space indentation".to_string(),
+                "format: trailing whitespace".to_string(),
+                "format: 17 columns over 12".to_string(),
+            ]
+        );
+        assert_eq!(
+            highlighter.describe(2),
+            vec![
+                "format: 16 columns over 12".to_string(),
+                "format: invisible character U+200B".to_string(),
+            ]
+        );
+        assert_eq!(
+            highlighter.describe(3),
+            vec!["format: 23 columns over 12".to_string()]
+        );
+
+        assert_eq!(text_limit(0, "81,100"), 80);
+        assert_eq!(text_limit(79, "+1"), 79);
+        assert_eq!(text_limit(0, "+1"), 0);
+        assert_eq!(text_limit(0, ""), 0);
+    }
+}
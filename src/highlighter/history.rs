@@ -0,0 +1,80 @@
+use super::*;
+
+#[derive(Debug)]
+pub struct HistoryHighlighter {
+    values: Vec<Highlight>,
+    texts: Vec<Vec<String>>,
+}
+
+impl Default for HistoryHighlighter {
+    fn default() -> Self {
+        Self {
+            values: Vec::with_capacity(LINE_CAPACITY),
+            texts: Vec::with_capacity(LINE_CAPACITY),
+        }
+    }
+}
+
+impl HistoryHighlighter {
+    /// grade the lines of the jumplist and the changelist, both ordered from the oldest,
+    /// into `levels` levels with the most recent entries the highest
+    pub fn sync(&mut self, len: usize, jumps: Vec<usize>, changes: Vec<usize>, levels: u64) {
+        self.values.clear();
+        self.values.resize(len, 0);
+        self.texts.clear();
+        self.texts.resize_with(len, Default::default);
+
+        for (name, lines) in [("jump", jumps), ("change", changes)].iter() {
+            let count = lines.len();
+            let mut seen = vec![false; len];
+
+            for (back, i) in lines.iter().rev().enumerate() {
+                if *i >= len {
+                    continue;
+                }
+
+                let level = ((count - back) as f64 / count as f64 * levels as f64).ceil();
+
+                self.values[*i] = self.values[*i].max(level as Highlight);
+
+                // only the most recent entry of a line is described
+                if !seen[*i] {
+                    seen[*i] = true;
+                    self.texts[*i].push(match back {
+                        0 => format!("{}: latest", name),
+                        back => format!("{}: {} back", name, back),
+                    });
+                }
+            }
+        }
+    }
+}
+
+impl Highlighter for HistoryHighlighter {
+    fn highlight(&self) -> Highlights {
+        self.values.clone()
+    }
+
+    fn describe(&self, i: usize) -> Vec<String> {
+        self.texts.get(i).cloned().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_highlighter_highlight() {
+        let mut highlighter = HistoryHighlighter::default();
+
+        highlighter.sync(5, vec![0, 3, 2, 3, 7], vec![1, 2], 3);
+
+        assert_eq!(highlighter.highlight(), vec![1, 2, 3, 3, 0]);
+        assert_eq!(
+            highlighter.describe(2),
+            vec!["jump: 2 back".to_string(), "change: latest".to_string()]
+        );
+        assert_eq!(highlighter.describe(3), vec!["jump: 1 back".to_string()]);
+    }
+}
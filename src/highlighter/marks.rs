@@ -0,0 +1,92 @@
+use super::*;
+
+#[derive(Debug, Default)]
+pub struct Mark {
+    pub i: usize,
+    pub name: char,
+}
+
+impl Mark {
+    fn level(&self) -> Highlight {
+        match self.name {
+            'a'..='z' => 2,
+            'A'..='Z' => 3,
+            _ => 1,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct MarksHighlighter {
+    values: Vec<String>,
+}
+
+impl Default for MarksHighlighter {
+    fn default() -> Self {
+        Self {
+            values: Vec::with_capacity(LINE_CAPACITY),
+        }
+    }
+}
+
+impl MarksHighlighter {
+    pub fn sync(&mut self, len: usize, marks: Vec<Mark>) {
+        self.values.clear();
+        self.values.resize_with(len, Default::default);
+
+        for mark in marks {
+            if mark.i >= len {
+                continue;
+            }
+
+            self.values[mark.i].push(mark.name);
+        }
+    }
+}
+
+impl Highlighter for MarksHighlighter {
+    fn highlight(&self) -> Highlights {
+        self.values
+            .iter()
+            .map(|names| {
+                names
+                    .chars()
+                    .map(|name| Mark { i: 0, name }.level())
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect()
+    }
+
+    fn describe(&self, i: usize) -> Vec<String> {
+        match self.values.get(i) {
+            Some(names) if !names.is_empty() => vec![format!("marks: {}", names)],
+            _ => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_marks_highlighter_highlight() {
+        let mut highlighter = MarksHighlighter::default();
+
+        highlighter.sync(
+            4,
+            vec![
+                Mark { i: 0, name: '.' },
+                Mark { i: 1, name: 'a' },
+                Mark { i: 1, name: '^' },
+                Mark { i: 3, name: 'B' },
+                Mark { i: 4, name: 'c' },
+            ],
+        );
+
+        assert_eq!(highlighter.highlight(), vec![1, 2, 0, 3]);
+        assert_eq!(highlighter.describe(1), vec!["marks: a^".to_string()]);
+        assert!(highlighter.describe(2).is_empty());
+    }
+}
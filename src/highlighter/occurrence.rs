@@ -0,0 +1,149 @@
+use super::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum OccurrenceKind {
+    None,
+    Text,
+    Read,
+    Write,
+}
+
+impl Default for OccurrenceKind {
+    fn default() -> Self {
+        Self::None
+    }
+}
+
+impl From<u64> for OccurrenceKind {
+    /// `DocumentHighlightKind` of the language server protocol
+    fn from(kind: u64) -> Self {
+        match kind {
+            2 => Self::Read,
+            3 => Self::Write,
+            _ => Self::Text,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Occurrence {
+    pub start: usize,
+    pub end: usize,
+    pub kind: OccurrenceKind,
+}
+
+#[derive(Debug)]
+pub struct OccurrenceHighlighter {
+    values: Vec<OccurrenceKind>,
+}
+
+impl Default for OccurrenceHighlighter {
+    fn default() -> Self {
+        Self {
+            values: Vec::with_capacity(LINE_CAPACITY),
+        }
+    }
+}
+
+impl OccurrenceHighlighter {
+    /// mark the lines containing `word` as a whole word
+    pub fn sync(&mut self, lines: &[String], word: &str) {
+        self.values.clear();
+        self.values.resize_with(lines.len(), Default::default);
+
+        if word.is_empty() || !word.chars().all(is_word_char) {
+            return;
+        }
+
+        for (value, line) in self.values.iter_mut().zip(lines) {
+            if contains_word(line, word) {
+                *value = OccurrenceKind::Text;
+            }
+        }
+    }
+
+    /// mark the lines of the ranges reported by a language server
+    pub fn sync_ranges(&mut self, len: usize, occurrences: Vec<Occurrence>) {
+        self.values.clear();
+        self.values.resize_with(len, Default::default);
+
+        for occurrence in occurrences {
+            for i in occurrence.start..=occurrence.end {
+                if i >= len {
+                    continue;
+                }
+
+                if self.values[i] < occurrence.kind {
+                    self.values[i] = occurrence.kind;
+                }
+            }
+        }
+    }
+}
+
+impl Highlighter for OccurrenceHighlighter {
+    fn highlight(&self) -> Highlights {
+        self.values
+            .iter()
+            .map(|val| match val {
+                OccurrenceKind::Text => 1,
+                OccurrenceKind::Read => 2,
+                OccurrenceKind::Write => 3,
+                _ => 0,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_occurrence_highlighter_highlight() {
+        let mut highlighter = OccurrenceHighlighter::default();
+
+        let lines = vec![
+            "let foo = 1;".to_string(),
+            "let foobar = foo_bar;".to_string(),
+            "bar(foo)".to_string(),
+            "".to_string(),
+        ];
+
+        highlighter.sync(&lines, "foo");
+
+        assert_eq!(highlighter.highlight(), vec![1, 0, 1, 0]);
+
+        highlighter.sync(&lines, "(foo");
+
+        assert_eq!(highlighter.highlight(), vec![0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_occurrence_highlighter_sync_ranges() {
+        let mut highlighter = OccurrenceHighlighter::default();
+
+        highlighter.sync_ranges(
+            4,
+            vec![
+                Occurrence {
+                    start: 0,
+                    end: 1,
+                    kind: OccurrenceKind::Read,
+                },
+                Occurrence {
+                    start: 1,
+                    end: 1,
+                    kind: OccurrenceKind::Write,
+                },
+                Occurrence {
+                    start: 3,
+                    end: 5,
+                    kind: OccurrenceKind::Text,
+                },
+            ],
+        );
+
+        assert_eq!(highlighter.highlight(), vec![2, 3, 0, 1]);
+    }
+}
@@ -0,0 +1,147 @@
+use super::*;
+
+#[derive(Debug, Default, Clone)]
+pub struct Symbol {
+    pub start: usize,
+    /// last line of the symbol, inclusive
+    pub end: usize,
+    pub kind: String,
+    pub name: String,
+}
+
+#[derive(Debug)]
+pub struct OutlineHighlighter {
+    values: Vec<Highlight>,
+    symbols: Vec<Symbol>,
+}
+
+impl Default for OutlineHighlighter {
+    fn default() -> Self {
+        Self {
+            values: Vec::with_capacity(LINE_CAPACITY),
+            symbols: vec![],
+        }
+    }
+}
+
+impl OutlineHighlighter {
+    /// draw a band over each symbol, alternating two levels between adjacent siblings
+    /// and between nesting depths, so that the boundaries of symbols stay visible
+    pub fn sync(&mut self, len: usize, mut symbols: Vec<Symbol>) {
+        self.values.clear();
+        self.values.resize(len, 0);
+
+        symbols.sort_by(|a, b| a.start.cmp(&b.start).then(b.end.cmp(&a.end)));
+
+        // last lines of the symbols containing the current one
+        let mut ends: Vec<usize> = vec![];
+        // number of the symbols seen at each depth within the current containing symbols
+        let mut siblings: Vec<usize> = vec![];
+
+        for symbol in &symbols {
+            while let Some(end) = ends.last() {
+                if *end >= symbol.start {
+                    break;
+                }
+
+                ends.pop();
+            }
+
+            let depth = ends.len();
+
+            siblings.truncate(depth + 1);
+            siblings.resize(depth + 1, 0);
+
+            let level = ((depth + siblings[depth]) % 2 + 1) as Highlight;
+
+            siblings[depth] += 1;
+
+            for i in symbol.start..=symbol.end {
+                if i >= len {
+                    break;
+                }
+
+                self.values[i] = level;
+            }
+
+            ends.push(symbol.end);
+        }
+
+        self.symbols = symbols;
+    }
+}
+
+impl Highlighter for OutlineHighlighter {
+    fn highlight(&self) -> Highlights {
+        self.values.clone()
+    }
+
+    fn describe(&self, i: usize) -> Vec<String> {
+        let path = self
+            .symbols
+            .iter()
+            .filter(|symbol| symbol.start <= i && i <= symbol.end)
+            .map(|symbol| format!("{} {}", symbol.kind, symbol.name))
+            .collect::<Vec<_>>();
+
+        if path.is_empty() {
+            return vec![];
+        }
+
+        vec![format!("symbol: {}", path.join(" > "))]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn symbol(start: usize, end: usize, kind: &str, name: &str) -> Symbol {
+        Symbol {
+            start,
+            end,
+            kind: kind.to_string(),
+            name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_outline_highlighter_highlight() {
+        let mut highlighter = OutlineHighlighter::default();
+
+        highlighter.sync(
+            8,
+            vec![
+                symbol(6, 9, "function", "main"),
+                symbol(0, 4, "impl", "Foo"),
+                symbol(1, 2, "method", "new"),
+                symbol(3, 4, "method", "bar"),
+            ],
+        );
+
+        assert_eq!(highlighter.highlight(), vec![1, 2, 2, 1, 1, 0, 2, 2]);
+        assert_eq!(
+            highlighter.describe(2),
+            vec!["symbol: impl Foo > method new".to_string()]
+        );
+        assert!(highlighter.describe(5).is_empty());
+    }
+
+    #[test]
+    fn test_outline_highlighter_siblings() {
+        let mut highlighter = OutlineHighlighter::default();
+
+        highlighter.sync(
+            8,
+            vec![
+                symbol(0, 1, "function", "foo"),
+                symbol(2, 3, "function", "bar"),
+                symbol(4, 7, "class", "Baz"),
+                symbol(5, 5, "method", "new"),
+                symbol(6, 6, "method", "qux"),
+            ],
+        );
+
+        assert_eq!(highlighter.highlight(), vec![1, 1, 2, 2, 1, 2, 1, 1]);
+    }
+}
@@ -0,0 +1,112 @@
+use super::*;
+
+#[derive(Debug, Default)]
+pub struct ProfileItem {
+    pub i: usize,
+    pub samples: f64,
+}
+
+#[derive(Debug)]
+pub struct ProfileHighlighter {
+    values: Vec<f64>,
+    levels: u64,
+    total: f64,
+}
+
+impl Default for ProfileHighlighter {
+    fn default() -> Self {
+        Self {
+            values: Vec::with_capacity(LINE_CAPACITY),
+            levels: 1,
+            total: 0.0,
+        }
+    }
+}
+
+impl ProfileHighlighter {
+    /// grade the samples of each line into `levels` levels, relative to the hottest line
+    pub fn sync(&mut self, len: usize, items: Vec<ProfileItem>, levels: u64) {
+        self.values.clear();
+        self.values.resize(len, 0.0);
+        self.levels = levels;
+        self.total = 0.0;
+
+        for item in items {
+            if item.i >= len {
+                continue;
+            }
+
+            self.values[item.i] += item.samples;
+            self.total += item.samples;
+        }
+    }
+}
+
+impl Highlighter for ProfileHighlighter {
+    fn highlight(&self) -> Highlights {
+        let max = self.values.iter().cloned().fold(0.0, f64::max);
+
+        self.values
+            .iter()
+            .map(|val| {
+                if *val > 0.0 {
+                    let level = (val / max * self.levels as f64).ceil() as Highlight;
+
+                    level.max(1).min(self.levels)
+                } else {
+                    0
+                }
+            })
+            .collect()
+    }
+
+    fn describe(&self, i: usize) -> Vec<String> {
+        match self.values.get(i) {
+            Some(val) if *val > 0.0 => vec![format!(
+                "profile: {:.1}% of samples",
+                val / self.total * 100.0
+            )],
+            _ => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_highlighter_highlight() {
+        let mut highlighter = ProfileHighlighter::default();
+
+        highlighter.sync(
+            5,
+            vec![
+                ProfileItem { i: 0, samples: 1.0 },
+                ProfileItem {
+                    i: 1,
+                    samples: 50.0,
+                },
+                ProfileItem {
+                    i: 2,
+                    samples: 100.0,
+                },
+                ProfileItem {
+                    i: 3,
+                    samples: 49.0,
+                },
+                ProfileItem {
+                    i: 9,
+                    samples: 10.0,
+                },
+            ],
+            4,
+        );
+
+        assert_eq!(highlighter.highlight(), vec![1, 2, 4, 2, 0]);
+        assert_eq!(
+            highlighter.describe(2),
+            vec!["profile: 50.0% of samples".to_string()]
+        );
+    }
+}
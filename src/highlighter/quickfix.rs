@@ -0,0 +1,122 @@
+use super::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum QuickfixKind {
+    None,
+    Entry,
+    Warning,
+    Error,
+}
+
+impl Default for QuickfixKind {
+    fn default() -> Self {
+        Self::None
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct QuickfixItem {
+    pub i: usize,
+    pub text: String,
+    pub kind: QuickfixKind,
+}
+
+#[derive(Debug)]
+pub struct QuickfixHighlighter {
+    values: Vec<QuickfixKind>,
+    texts: Vec<Vec<String>>,
+}
+
+impl Default for QuickfixHighlighter {
+    fn default() -> Self {
+        Self {
+            values: Vec::with_capacity(LINE_CAPACITY),
+            texts: Vec::with_capacity(LINE_CAPACITY),
+        }
+    }
+}
+
+impl QuickfixHighlighter {
+    pub fn sync(&mut self, len: usize, items: Vec<QuickfixItem>) {
+        self.values.clear();
+        self.values.resize_with(len, Default::default);
+        self.texts.clear();
+        self.texts.resize_with(len, Default::default);
+
+        for item in items {
+            if item.i >= len {
+                continue;
+            }
+
+            // several entries may point to the same line, keep the most severe one
+            if self.values[item.i] < item.kind {
+                self.values[item.i] = item.kind;
+            }
+
+            self.texts[item.i].push(item.text);
+        }
+    }
+}
+
+impl Highlighter for QuickfixHighlighter {
+    fn highlight(&self) -> Highlights {
+        self.values
+            .iter()
+            .map(|val| match val {
+                QuickfixKind::Entry => 1,
+                QuickfixKind::Warning => 2,
+                QuickfixKind::Error => 3,
+                _ => 0,
+            })
+            .collect()
+    }
+
+    fn describe(&self, i: usize) -> Vec<String> {
+        self.texts
+            .get(i)
+            .map(|texts| texts.iter().map(|text| format!("qf: {}", text)).collect())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quickfix_highlighter_highlight() {
+        let mut highlighter = QuickfixHighlighter::default();
+
+        highlighter.sync(
+            4,
+            vec![
+                QuickfixItem {
+                    i: 0,
+                    text: "foo".to_string(),
+                    kind: QuickfixKind::Entry,
+                },
+                QuickfixItem {
+                    i: 2,
+                    text: "bar".to_string(),
+                    kind: QuickfixKind::Error,
+                },
+                QuickfixItem {
+                    i: 2,
+                    text: "baz".to_string(),
+                    kind: QuickfixKind::Warning,
+                },
+                QuickfixItem {
+                    i: 4,
+                    text: "hoge".to_string(),
+                    kind: QuickfixKind::Entry,
+                },
+            ],
+        );
+
+        assert_eq!(highlighter.highlight(), vec![1, 0, 3, 0]);
+        assert_eq!(
+            highlighter.describe(2),
+            vec!["qf: bar".to_string(), "qf: baz".to_string()]
+        );
+    }
+}
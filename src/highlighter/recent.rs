@@ -0,0 +1,115 @@
+use super::*;
+use std::cmp::min;
+use std::time::{Duration, Instant};
+
+#[derive(Debug)]
+pub struct RecentHighlighter {
+    /// when each line was last edited
+    values: Vec<Option<Instant>>,
+    levels: u64,
+    decay: Duration,
+    now: Option<Instant>,
+}
+
+impl Default for RecentHighlighter {
+    fn default() -> Self {
+        Self {
+            values: Vec::with_capacity(LINE_CAPACITY),
+            levels: 1,
+            decay: Duration::default(),
+            now: None,
+        }
+    }
+}
+
+impl RecentHighlighter {
+    /// forget every edit of a buffer of `len` lines
+    pub fn reset(&mut self, len: usize) {
+        self.values.clear();
+        self.values.resize(len, None);
+    }
+
+    /// mark the lines from `first` replacing the old lines until `last` as edited at `now`.
+    /// deleted lines mark the line following them.
+    pub fn update(&mut self, first: usize, last: usize, count: usize, now: Instant) {
+        let len = self.values.len();
+        let first = min(first, len);
+        let last = min(last, len).max(first);
+
+        self.values.splice(first..last, vec![Some(now); count]);
+
+        if count == 0 && first < self.values.len() {
+            self.values[first] = Some(now);
+        }
+    }
+
+    /// grade the edits into `levels` levels at `now`, fading out in `decay`
+    pub fn sync(&mut self, len: usize, now: Instant, decay: Duration, levels: u64) {
+        self.values.resize(len, None);
+        self.now = Some(now);
+        self.decay = decay;
+        self.levels = levels;
+    }
+
+    fn age(&self, i: usize) -> Option<Duration> {
+        let time = (*self.values.get(i)?)?;
+        let age = self.now?.saturating_duration_since(time);
+
+        if age < self.decay {
+            Some(age)
+        } else {
+            None
+        }
+    }
+}
+
+impl Highlighter for RecentHighlighter {
+    fn highlight(&self) -> Highlights {
+        (0..self.values.len())
+            .map(|i| match self.age(i) {
+                Some(age) => {
+                    let rest = 1.0 - age.as_secs_f64() / self.decay.as_secs_f64();
+
+                    ((rest * self.levels as f64).ceil() as Highlight).max(1)
+                }
+                None => 0,
+            })
+            .collect()
+    }
+
+    fn describe(&self, i: usize) -> Vec<String> {
+        match self.age(i) {
+            Some(age) => vec![format!("edited: {}", to_age(age.as_secs()))],
+            None => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recent_highlighter_highlight() {
+        let mut highlighter = RecentHighlighter::default();
+        let start = Instant::now();
+
+        highlighter.reset(5);
+        highlighter.update(1, 2, 2, start);
+        highlighter.update(4, 4, 1, start + Duration::from_secs(150));
+        highlighter.update(0, 1, 0, start + Duration::from_secs(200));
+
+        highlighter.sync(
+            6,
+            start + Duration::from_secs(210),
+            Duration::from_secs(200),
+            4,
+        );
+
+        assert_eq!(highlighter.highlight(), vec![4, 0, 0, 3, 0, 0]);
+        assert_eq!(
+            highlighter.describe(3),
+            vec!["edited: 1 minute ago".to_string()]
+        );
+    }
+}
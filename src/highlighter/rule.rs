@@ -0,0 +1,87 @@
+use super::*;
+use regex::Regex;
+use std::cmp::min;
+
+#[derive(Debug)]
+pub struct RuleHighlighter {
+    /// first text matched on each line
+    values: Vec<Option<String>>,
+}
+
+impl Default for RuleHighlighter {
+    fn default() -> Self {
+        Self {
+            values: Vec::with_capacity(LINE_CAPACITY),
+        }
+    }
+}
+
+impl RuleHighlighter {
+    /// match every line against `regex`, which matches nothing when the rule does not apply
+    pub fn sync(&mut self, lines: &[String], regex: Option<&Regex>) {
+        self.values = lines.iter().map(|line| first_match(line, regex)).collect();
+    }
+
+    /// match the lines from `first` replacing the old lines until `last`
+    pub fn update(&mut self, first: usize, last: usize, lines: &[String], regex: Option<&Regex>) {
+        let len = self.values.len();
+        let first = min(first, len);
+        let last = min(last, len).max(first);
+
+        self.values.splice(
+            first..last,
+            lines.iter().map(|line| first_match(line, regex)),
+        );
+    }
+}
+
+impl Highlighter for RuleHighlighter {
+    fn highlight(&self) -> Highlights {
+        self.values
+            .iter()
+            .map(|value| if value.is_some() { 1 } else { 0 })
+            .collect()
+    }
+
+    fn describe(&self, i: usize) -> Vec<String> {
+        match self.values.get(i) {
+            Some(Some(text)) => vec![format!("match: {}", text.trim())],
+            _ => vec![],
+        }
+    }
+}
+
+fn first_match(line: &str, regex: Option<&Regex>) -> Option<String> {
+    regex?.find(line).map(|m| m.as_str().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rule_highlighter_update() {
+        let mut highlighter = RuleHighlighter::default();
+        let regex = Regex::new(r"\bunsafe\b|println!").unwrap();
+
+        highlighter.sync(
+            &[
+                "fn main() {".to_string(),
+                "    unsafe { foo() }".to_string(),
+                "    println!(\"{}\", unsafe_bar());".to_string(),
+            ],
+            Some(&regex),
+        );
+
+        assert_eq!(highlighter.highlight(), vec![0, 1, 1]);
+        assert_eq!(highlighter.describe(2), vec!["match: println!".to_string()]);
+
+        highlighter.update(0, 2, &["let a = unsafe_bar();".to_string()], Some(&regex));
+
+        assert_eq!(highlighter.highlight(), vec![0, 1]);
+
+        highlighter.sync(&["unsafe {}".to_string()], None);
+
+        assert_eq!(highlighter.highlight(), vec![0]);
+    }
+}
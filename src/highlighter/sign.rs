@@ -0,0 +1,82 @@
+use super::*;
+
+#[derive(Debug, Default)]
+pub struct SignItem {
+    pub i: usize,
+    pub name: String,
+}
+
+#[derive(Debug)]
+pub struct SignHighlighter {
+    values: Vec<Vec<String>>,
+}
+
+impl Default for SignHighlighter {
+    fn default() -> Self {
+        Self {
+            values: Vec::with_capacity(LINE_CAPACITY),
+        }
+    }
+}
+
+impl SignHighlighter {
+    pub fn sync(&mut self, len: usize, items: Vec<SignItem>) {
+        self.values.clear();
+        self.values.resize_with(len, Default::default);
+
+        for item in items {
+            if item.i >= len {
+                continue;
+            }
+
+            if !self.values[item.i].contains(&item.name) {
+                self.values[item.i].push(item.name);
+            }
+        }
+    }
+}
+
+impl Highlighter for SignHighlighter {
+    fn highlight(&self) -> Highlights {
+        self.values
+            .iter()
+            .map(|names| if names.is_empty() { 0 } else { 1 })
+            .collect()
+    }
+
+    fn describe(&self, i: usize) -> Vec<String> {
+        self.values
+            .get(i)
+            .map(|names| names.iter().map(|name| format!("sign: {}", name)).collect())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_highlighter_highlight() {
+        let mut highlighter = SignHighlighter::default();
+        let item = |i, name: &str| SignItem {
+            i,
+            name: name.to_string(),
+        };
+
+        highlighter.sync(
+            3,
+            vec![
+                item(1, "DapBreakpoint"),
+                item(1, "DapBreakpoint"),
+                item(5, "DapStopped"),
+            ],
+        );
+
+        assert_eq!(highlighter.highlight(), vec![0, 1, 0]);
+        assert_eq!(
+            highlighter.describe(1),
+            vec!["sign: DapBreakpoint".to_string()]
+        );
+    }
+}
@@ -0,0 +1,99 @@
+use super::*;
+use std::cmp::min;
+
+/// a misspelled word, which is rare, local or not capitalized unless `bad`
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SpellItem {
+    pub word: String,
+    pub bad: bool,
+}
+
+#[derive(Debug)]
+pub struct SpellHighlighter {
+    values: Vec<Vec<SpellItem>>,
+}
+
+impl Default for SpellHighlighter {
+    fn default() -> Self {
+        Self {
+            values: Vec::with_capacity(LINE_CAPACITY),
+        }
+    }
+}
+
+impl SpellHighlighter {
+    /// replace the misspelled words of every line
+    pub fn sync(&mut self, lines: Vec<Vec<SpellItem>>) {
+        self.values = lines;
+    }
+
+    /// replace the lines from `first` until `last` with the misspelled words of new lines
+    pub fn update(&mut self, first: usize, last: usize, lines: Vec<Vec<SpellItem>>) {
+        let len = self.values.len();
+        let first = min(first, len);
+        let last = min(last, len).max(first);
+
+        self.values.splice(first..last, lines);
+    }
+}
+
+impl Highlighter for SpellHighlighter {
+    fn highlight(&self) -> Highlights {
+        self.values
+            .iter()
+            .map(|items| match items.iter().map(|item| item.bad).max() {
+                Some(true) => 2,
+                Some(false) => 1,
+                None => 0,
+            })
+            .collect()
+    }
+
+    fn describe(&self, i: usize) -> Vec<String> {
+        match self.values.get(i) {
+            Some(items) if !items.is_empty() => vec![format!(
+                "spell: {}",
+                items
+                    .iter()
+                    .map(|item| &item.word[..])
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )],
+            _ => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spell_highlighter_update() {
+        let mut highlighter = SpellHighlighter::default();
+        let item = |word: &str, bad| SpellItem {
+            word: word.to_string(),
+            bad,
+        };
+
+        highlighter.sync(vec![
+            vec![item("teh", true)],
+            vec![],
+            vec![item("english", false)],
+        ]);
+
+        assert_eq!(highlighter.highlight(), vec![2, 0, 1]);
+
+        highlighter.update(
+            0,
+            1,
+            vec![vec![], vec![item("recieve", true), item("foo", false)]],
+        );
+
+        assert_eq!(highlighter.highlight(), vec![0, 2, 0, 1]);
+        assert_eq!(
+            highlighter.describe(1),
+            vec!["spell: recieve, foo".to_string()]
+        );
+    }
+}
@@ -0,0 +1,137 @@
+use crate::watch::same_file;
+use crate::xml;
+use anyhow::{bail, Result};
+
+/// a failed test case with the location of its failure
+#[derive(Debug, PartialEq)]
+pub struct TestFailure {
+    pub file: String,
+    pub lnum: u64,
+    pub name: String,
+    pub message: String,
+    /// the test raised an unexpected error rather than failing an assertion
+    pub error: bool,
+}
+
+/// failures in a JUnit XML report
+#[derive(Debug, Default)]
+pub struct TestResults {
+    failures: Vec<TestFailure>,
+}
+
+impl TestResults {
+    /// parse a report, taking the location from the `file` and `line` attributes of each test case
+    /// or of its test suite. failures without a line are left out.
+    pub fn parse(text: &str) -> Result<Self> {
+        let tags = xml::tags(text);
+
+        if !tags
+            .iter()
+            .any(|tag| tag.name == "testsuite" || tag.name == "testsuites")
+        {
+            bail!("missing testsuite element");
+        }
+
+        let mut failures = vec![];
+        let mut suite_file = None;
+        let mut case = None;
+
+        for tag in &tags {
+            match tag.name {
+                "testsuite" => {
+                    suite_file = tag.attr("file");
+                    case = None;
+                }
+                // a failure belongs to the test case enclosing it
+                "testcase" if tag.end || tag.empty => case = None,
+                "testcase" => {
+                    case = Some((
+                        tag.attr("file").or(suite_file).unwrap_or(""),
+                        tag.attr("line").and_then(|line| line.parse::<u64>().ok()),
+                        tag.attr("name").unwrap_or(""),
+                    ))
+                }
+                _ if tag.end => {}
+                "failure" | "error" => {
+                    let (file, lnum, name) = match case {
+                        Some((file, Some(lnum), name)) if !file.is_empty() => (file, lnum, name),
+                        _ => continue,
+                    };
+
+                    let message = tag
+                        .attr("message")
+                        .or_else(|| tag.attr("type"))
+                        .unwrap_or("failed");
+
+                    failures.push(TestFailure {
+                        file: file.to_string(),
+                        lnum,
+                        name: name.to_string(),
+                        // messages may span lines, and only the first fits a hover row
+                        message: message.lines().next().unwrap_or("").to_string(),
+                        error: tag.name == "error",
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        Ok(TestResults { failures })
+    }
+
+    /// failures located in the file `name`
+    pub fn failures(&self, name: &str) -> Vec<&TestFailure> {
+        self.failures
+            .iter()
+            .filter(|failure| same_file(name, &failure.file))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_test_results_parse() {
+        let results = TestResults::parse(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites>
+  <testsuite name="foo" file="tests/foo.rs">
+    <testcase name="passes" line="3"/>
+    <testcase name="fails" line="10">
+      <failure message="expected 1, got 2&#10;at tests/foo.rs:12" type="assert"/>
+    </testcase>
+    <testcase name="panics" file="src/bar.rs" line="20">
+      <error type="panic">backtrace</error>
+    </testcase>
+    <testcase name="unknown">
+      <failure message="no line"/>
+    </testcase>
+    <testcase name="closed" line="30"/>
+    <failure message="outside of a test case"/>
+  </testsuite>
+  <testsuite name="bar" file="src/bar.rs">
+    <failure message="suite setup failed"/>
+  </testsuite>
+</testsuites>"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            results.failures("/project/tests/foo.rs"),
+            vec![&TestFailure {
+                file: "tests/foo.rs".to_string(),
+                lnum: 10,
+                name: "fails".to_string(),
+                message: "expected 1, got 2".to_string(),
+                error: false,
+            }]
+        );
+        assert_eq!(results.failures("/project/src/bar.rs").len(), 1);
+        assert_eq!(results.failures("/project/src/bar.rs")[0].message, "panic");
+        assert!(results.failures("/project/src/baz.rs").is_empty());
+
+        assert!(TestResults::parse("<coverage/>").is_err());
+    }
+}
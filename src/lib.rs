@@ -1,4 +1,18 @@
+pub mod blame;
+pub mod cache;
+pub mod config;
+pub mod coverage;
+pub mod extmarks;
 pub mod highlighter;
+pub mod junit;
+pub mod lsp;
 pub mod message;
 pub mod picomap;
+pub mod profile;
+pub mod rules;
 pub mod server;
+pub mod signs;
+pub mod spell;
+pub mod tags;
+pub mod watch;
+pub mod xml;
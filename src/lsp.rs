@@ -0,0 +1,104 @@
+//! lua chunks run with `nvim_exec_lua` to ask the language servers of the current buffer
+
+/// request `textDocument/documentHighlight` at the cursor of the buffer `bufnr` without waiting,
+/// and send the ranges back to `channel` as `document_highlight` with `bufnr`, `tick` and `word`.
+/// returns false when no language server is attached.
+pub const DOCUMENT_HIGHLIGHT: &str = r#"
+local channel, bufnr, tick, word = ...
+
+-- `buf_get_clients` is deprecated where `get_clients` exists
+local clients
+if vim.lsp.get_clients then
+  clients = vim.lsp.get_clients({ bufnr = bufnr })
+else
+  clients = vim.lsp.buf_get_clients(bufnr)
+end
+
+local _, client = next(clients)
+if not client then
+  return false
+end
+
+local params = vim.lsp.util.make_position_params(0, client.offset_encoding)
+
+vim.lsp.buf_request_all(bufnr, 'textDocument/documentHighlight', params, function(responses)
+  local ranges = {}
+
+  for _, response in pairs(responses or {}) do
+    for _, highlight in ipairs(response.result or {}) do
+      table.insert(ranges, {
+        highlight.range.start.line,
+        highlight.range['end'].line,
+        highlight.kind or 1,
+      })
+    end
+  end
+
+  vim.rpcnotify(channel, 'document_highlight', bufnr, tick, word, ranges)
+end)
+
+return true
+"#;
+
+/// request `textDocument/documentSymbol` of the buffer `bufnr` without waiting, and send the
+/// symbols back to `channel` as `document_symbol` with `bufnr`, `tick` and the buffer name,
+/// as a flat list of `[start, end, kind, name]`. returns false when no language server is attached.
+pub const DOCUMENT_SYMBOL: &str = r#"
+local channel, bufnr, tick = ...
+
+-- `buf_get_clients` is deprecated where `get_clients` exists
+local clients
+if vim.lsp.get_clients then
+  clients = vim.lsp.get_clients({ bufnr = bufnr })
+else
+  clients = vim.lsp.buf_get_clients(bufnr)
+end
+
+if next(clients) == nil then
+  return false
+end
+
+local params = { textDocument = vim.lsp.util.make_text_document_params(bufnr) }
+
+vim.lsp.buf_request_all(bufnr, 'textDocument/documentSymbol', params, function(responses)
+  local symbols = {}
+
+  -- DocumentSymbol has a range and children, SymbolInformation a location
+  local function add(items)
+    for _, item in ipairs(items or {}) do
+      local range = item.range or (item.location and item.location.range)
+
+      if range then
+        table.insert(symbols, { range.start.line, range['end'].line, item.kind, item.name })
+      end
+
+      add(item.children)
+    end
+  end
+
+  for _, response in pairs(responses or {}) do
+    add(response.result)
+  end
+
+  vim.rpcnotify(channel, 'document_symbol', bufnr, tick, vim.api.nvim_buf_get_name(bufnr), symbols)
+end)
+
+return true
+"#;
+
+/// label of a `SymbolKind` drawn as an outline band, `None` for the other kinds
+pub fn symbol_kind(kind: u64) -> Option<&'static str> {
+    match kind {
+        2 => Some("module"),
+        3 => Some("namespace"),
+        5 => Some("class"),
+        6 => Some("method"),
+        9 => Some("constructor"),
+        10 => Some("enum"),
+        11 => Some("interface"),
+        12 => Some("function"),
+        19 => Some("impl"),
+        23 => Some("struct"),
+        _ => None,
+    }
+}
@@ -7,6 +7,20 @@ pub enum Message {
     Show,
     Close,
     Resize,
+    Configure,
+    Evict,
+    Quickfix,
+    Hover,
+    Unhover,
+    Occurrence,
+    DocumentHighlight,
+    JumpConflict,
+    Outline,
+    DocumentSymbol,
+    Blame,
+    BufLines,
+    BufChangedTick,
+    BufDetach,
     Unknown(String),
 }
 
@@ -17,6 +31,20 @@ impl From<String> for Message {
             "show" => Message::Show,
             "close" => Message::Close,
             "resize" => Message::Resize,
+            "configure" => Message::Configure,
+            "evict" => Message::Evict,
+            "quickfix" => Message::Quickfix,
+            "hover" => Message::Hover,
+            "unhover" => Message::Unhover,
+            "occurrence" => Message::Occurrence,
+            "document_highlight" => Message::DocumentHighlight,
+            "jump_conflict" => Message::JumpConflict,
+            "outline" => Message::Outline,
+            "document_symbol" => Message::DocumentSymbol,
+            "blame" => Message::Blame,
+            "nvim_buf_lines_event" => Message::BufLines,
+            "nvim_buf_changedtick_event" => Message::BufChangedTick,
+            "nvim_buf_detach_event" => Message::BufDetach,
             _ => Message::Unknown(event),
         }
     }
@@ -41,6 +69,7 @@ impl From<String> for LocationType {
 
 #[derive(Debug)]
 pub struct Location {
+    pub bufnr: u64,
     pub lnum: u64,
     pub typ: LocationType,
     pub text: String,
@@ -53,6 +82,11 @@ impl TryFrom<&Value> for Location {
         let fields = value.as_map().with_context(|| "invalid location value")?;
 
         Ok(Location {
+            bufnr: fields
+                .iter()
+                .find(|field| field.0.as_str() == Some("bufnr"))
+                .and_then(|field| field.1.as_u64())
+                .unwrap_or(0),
             lnum: fields
                 .iter()
                 .find(|field| field.0.as_str() == Some("lnum"))
@@ -83,6 +117,202 @@ impl TryFrom<&Value> for Location {
     }
 }
 
+/// entries of the quickfix list, sent when it is updated
+#[derive(Debug)]
+pub struct QuickfixPayload {
+    pub locations: Vec<Location>,
+}
+
+impl TryFrom<Vec<Value>> for QuickfixPayload {
+    type Error = anyhow::Error;
+
+    fn try_from(values: Vec<Value>) -> Result<QuickfixPayload> {
+        Ok(QuickfixPayload {
+            locations: values
+                .first()
+                .and_then(|value| value.as_array())
+                .with_context(|| "invalid quickfix field")?
+                .iter()
+                .map(Location::try_from)
+                .collect::<Result<Vec<Location>>>()
+                .with_context(|| "invalid location value")?,
+        })
+    }
+}
+
+/// an entry of `getmarklist()`
+#[derive(Debug)]
+pub struct MarkEntry {
+    pub name: char,
+    pub bufnr: u64,
+    pub lnum: u64,
+}
+
+impl TryFrom<&Value> for MarkEntry {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &Value) -> Result<Self> {
+        let fields = value.as_map().with_context(|| "invalid mark value")?;
+
+        let mark = fields
+            .iter()
+            .find(|field| field.0.as_str() == Some("mark"))
+            .with_context(|| "missing mark name")?
+            .1
+            .as_str()
+            .with_context(|| "invalid mark name")?;
+
+        let pos = fields
+            .iter()
+            .find(|field| field.0.as_str() == Some("pos"))
+            .with_context(|| "missing mark pos")?
+            .1
+            .as_array()
+            .with_context(|| "invalid mark pos")?;
+
+        Ok(MarkEntry {
+            // names are given with a leading quote, like 'a
+            name: mark.chars().last().with_context(|| "empty mark name")?,
+            bufnr: pos[0].as_u64().with_context(|| "invalid mark bufnr")?,
+            lnum: pos[1].as_u64().with_context(|| "invalid mark lnum")?,
+        })
+    }
+}
+
+/// an entry of `getjumplist()` or `getchangelist()`, whose bufnr is 0 for changes
+#[derive(Debug)]
+pub struct PositionEntry {
+    pub bufnr: u64,
+    pub lnum: u64,
+}
+
+impl TryFrom<&Value> for PositionEntry {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &Value) -> Result<Self> {
+        let fields = value.as_map().with_context(|| "invalid position value")?;
+
+        let field = |key: &str| {
+            fields
+                .iter()
+                .find(|field| field.0.as_str() == Some(key))
+                .and_then(|field| field.1.as_u64())
+        };
+
+        Ok(PositionEntry {
+            bufnr: field("bufnr").unwrap_or(0),
+            lnum: field("lnum").with_context(|| "invalid position lnum")?,
+        })
+    }
+}
+
+/// a sign of `sign_getplaced()`, or a sign defined with an extmark
+#[derive(Debug)]
+pub struct PlacedSign {
+    pub lnum: u64,
+    pub name: String,
+    pub group: String,
+}
+
+impl TryFrom<&Value> for PlacedSign {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &Value) -> Result<Self> {
+        let fields = value.as_map().with_context(|| "invalid sign value")?;
+
+        let field = |key: &str| {
+            fields
+                .iter()
+                .find(|field| field.0.as_str() == Some(key))
+                .map(|field| &field.1)
+        };
+
+        Ok(PlacedSign {
+            lnum: field("lnum")
+                .and_then(|value| value.as_u64())
+                .with_context(|| "invalid sign lnum")?,
+            name: field("name")
+                .and_then(|value| value.as_str())
+                .with_context(|| "invalid sign name")?
+                .to_string(),
+            // signs placed without a group belong to the global group ''
+            group: field("group")
+                .and_then(|value| value.as_str())
+                .unwrap_or("")
+                .to_string(),
+        })
+    }
+}
+
+/// an extmark with a highlight group, covering the 0-based rows from `start` to `end` inclusive
+#[derive(Debug)]
+pub struct Extmark {
+    pub start: u64,
+    pub end: u64,
+    pub hl_group: String,
+    pub namespace: String,
+}
+
+impl TryFrom<&Value> for Extmark {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &Value) -> Result<Self> {
+        let fields = value.as_map().with_context(|| "invalid extmark value")?;
+
+        let field = |key: &str| {
+            fields
+                .iter()
+                .find(|field| field.0.as_str() == Some(key))
+                .map(|field| &field.1)
+                .with_context(|| format!("missing extmark {}", key))
+        };
+
+        Ok(Extmark {
+            start: field("start")?
+                .as_u64()
+                .with_context(|| "invalid extmark start")?,
+            end: field("end")?
+                .as_u64()
+                .with_context(|| "invalid extmark end")?,
+            hl_group: field("hl_group")?
+                .as_str()
+                .with_context(|| "invalid extmark hl_group")?
+                .to_string(),
+            namespace: field("namespace")?
+                .as_str()
+                .with_context(|| "invalid extmark namespace")?
+                .to_string(),
+        })
+    }
+}
+
+/// a `[word, type]` misspelled word of `vim.spell.check()`
+#[derive(Debug)]
+pub struct BadWord {
+    pub word: String,
+    /// one of `bad`, `rare`, `local` and `caps`
+    pub kind: String,
+}
+
+impl TryFrom<&Value> for BadWord {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &Value) -> Result<Self> {
+        let values = value.as_array().with_context(|| "invalid bad word value")?;
+
+        Ok(Self {
+            word: values[0]
+                .as_str()
+                .with_context(|| "invalid bad word")?
+                .to_string(),
+            kind: values[1]
+                .as_str()
+                .with_context(|| "invalid bad word type")?
+                .to_string(),
+        })
+    }
+}
+
 #[derive(Debug)]
 pub struct Hunk {
     pub lnum: u64,
@@ -102,6 +332,149 @@ impl TryFrom<&Value> for Hunk {
     }
 }
 
+/// a `[start, end, kind]` range of `textDocument/documentHighlight`, with 0-based lines
+#[derive(Debug)]
+pub struct DocumentHighlight {
+    pub start: u64,
+    pub end: u64,
+    pub kind: u64,
+}
+
+impl TryFrom<&Value> for DocumentHighlight {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &Value) -> Result<Self> {
+        let values = value
+            .as_array()
+            .with_context(|| "invalid document highlight value")?;
+
+        Ok(Self {
+            start: values[0]
+                .as_u64()
+                .with_context(|| "invalid document highlight start")?,
+            end: values[1]
+                .as_u64()
+                .with_context(|| "invalid document highlight end")?,
+            kind: values[2]
+                .as_u64()
+                .with_context(|| "invalid document highlight kind")?,
+        })
+    }
+}
+
+/// references sent back by a document highlight request of the word `word`
+/// at the changedtick `tick` of the buffer `bufnr`
+#[derive(Debug)]
+pub struct DocumentHighlightPayload {
+    pub bufnr: i64,
+    pub tick: i64,
+    pub word: String,
+    pub highlights: Vec<DocumentHighlight>,
+}
+
+impl TryFrom<Vec<Value>> for DocumentHighlightPayload {
+    type Error = anyhow::Error;
+
+    fn try_from(values: Vec<Value>) -> Result<Self> {
+        Ok(DocumentHighlightPayload {
+            bufnr: values
+                .first()
+                .and_then(|value| value.as_i64())
+                .with_context(|| "invalid bufnr")?,
+            tick: values
+                .get(1)
+                .and_then(|value| value.as_i64())
+                .with_context(|| "invalid changedtick")?,
+            word: values
+                .get(2)
+                .and_then(|value| value.as_str())
+                .with_context(|| "invalid word")?
+                .to_owned(),
+            highlights: values
+                .get(3)
+                .and_then(|value| value.as_array())
+                .with_context(|| "invalid document highlights")?
+                .iter()
+                .map(DocumentHighlight::try_from)
+                .collect::<Result<Vec<_>>>()?,
+        })
+    }
+}
+
+/// a `[start, end, kind, name]` symbol of `textDocument/documentSymbol`, with 0-based lines
+#[derive(Debug)]
+pub struct DocumentSymbol {
+    pub start: u64,
+    pub end: u64,
+    pub kind: u64,
+    pub name: String,
+}
+
+impl TryFrom<&Value> for DocumentSymbol {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &Value) -> Result<Self> {
+        let values = value
+            .as_array()
+            .with_context(|| "invalid document symbol value")?;
+
+        Ok(Self {
+            start: values[0]
+                .as_u64()
+                .with_context(|| "invalid document symbol start")?,
+            end: values[1]
+                .as_u64()
+                .with_context(|| "invalid document symbol end")?,
+            kind: values[2]
+                .as_u64()
+                .with_context(|| "invalid document symbol kind")?,
+            name: values[3]
+                .as_str()
+                .with_context(|| "invalid document symbol name")?
+                .to_owned(),
+        })
+    }
+}
+
+/// symbols sent back by a document symbol request at the changedtick `tick`
+/// of the buffer `bufnr` named `name`
+#[derive(Debug)]
+pub struct DocumentSymbolPayload {
+    pub bufnr: i64,
+    pub tick: i64,
+    pub name: String,
+    pub symbols: Vec<DocumentSymbol>,
+}
+
+impl TryFrom<Vec<Value>> for DocumentSymbolPayload {
+    type Error = anyhow::Error;
+
+    fn try_from(values: Vec<Value>) -> Result<Self> {
+        Ok(DocumentSymbolPayload {
+            bufnr: values
+                .first()
+                .and_then(|value| value.as_i64())
+                .with_context(|| "invalid bufnr")?,
+            tick: values
+                .get(1)
+                .and_then(|value| value.as_i64())
+                .with_context(|| "invalid changedtick")?,
+            name: values
+                .get(2)
+                .and_then(|value| value.as_str())
+                .with_context(|| "invalid buffer name")?
+                .to_owned(),
+            symbols: values
+                .get(3)
+                .and_then(|value| value.as_array())
+                .with_context(|| "invalid document symbols")?
+                .iter()
+                .map(DocumentSymbol::try_from)
+                .collect::<Result<Vec<_>>>()?,
+        })
+    }
+}
+
 #[derive(Debug)]
 pub struct SyncPayload {
     pub locations: Vec<Location>,
@@ -131,3 +504,120 @@ impl TryFrom<Vec<Value>> for SyncPayload {
         })
     }
 }
+
+/// lines changed in a buffer attached by `nvim_buf_attach()`.
+/// the lines from `first` until `last` are replaced with `lines`, and `last` is `None` on the
+/// first event, which sends the whole buffer.
+#[derive(Debug)]
+pub struct BufLinesEvent {
+    pub buf: Value,
+    pub first: u64,
+    pub last: Option<u64>,
+    pub lines: Vec<String>,
+}
+
+impl TryFrom<Vec<Value>> for BufLinesEvent {
+    type Error = anyhow::Error;
+
+    fn try_from(values: Vec<Value>) -> Result<Self> {
+        Ok(BufLinesEvent {
+            buf: values.first().with_context(|| "missing buffer")?.clone(),
+            first: values
+                .get(2)
+                .and_then(|value| value.as_u64())
+                .with_context(|| "invalid first line")?,
+            last: values.get(3).and_then(|value| value.as_u64()),
+            lines: values
+                .get(4)
+                .and_then(|value| value.as_array())
+                .with_context(|| "invalid line data")?
+                .iter()
+                .map(|value| {
+                    value
+                        .as_str()
+                        .map(|line| line.to_owned())
+                        .with_context(|| "invalid line")
+                })
+                .collect::<Result<Vec<_>>>()?,
+        })
+    }
+}
+
+/// geometry of a window returned by `getwininfo()`
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct WinInfo {
+    pub winrow: u64,
+    pub wincol: u64,
+    pub width: u64,
+    pub height: u64,
+    pub textoff: u64,
+    pub winbar: u64,
+}
+
+impl TryFrom<&Value> for WinInfo {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &Value) -> Result<Self> {
+        let fields = value.as_map().with_context(|| "invalid wininfo value")?;
+
+        let field = |key: &str| {
+            fields
+                .iter()
+                .find(|field| field.0.as_str() == Some(key))
+                .and_then(|field| field.1.as_u64())
+        };
+
+        Ok(WinInfo {
+            winrow: field("winrow").with_context(|| "invalid wininfo winrow")?,
+            wincol: field("wincol").with_context(|| "invalid wininfo wincol")?,
+            width: field("width").with_context(|| "invalid wininfo width")?,
+            height: field("height").with_context(|| "invalid wininfo height")?,
+            textoff: field("textoff").unwrap_or(0),
+            // winbar is only reported by recent versions of neovim
+            winbar: field("winbar").unwrap_or(0),
+        })
+    }
+}
+
+/// cursor column and the extent of each visible non-blank line,
+/// in display columns relative to the text area of a window
+#[derive(Debug, Default)]
+pub struct TextExtent {
+    pub cursor: u64,
+    pub lines: Vec<(u64, u64)>,
+}
+
+impl TryFrom<&Value> for TextExtent {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &Value) -> Result<Self> {
+        let fields = value.as_map().with_context(|| "invalid extent value")?;
+
+        let field = |key: &str| {
+            fields
+                .iter()
+                .find(|field| field.0.as_str() == Some(key))
+                .map(|field| &field.1)
+                .with_context(|| format!("missing extent {}", key))
+        };
+
+        Ok(TextExtent {
+            cursor: field("cursor")?
+                .as_u64()
+                .with_context(|| "invalid extent cursor")?,
+            lines: field("lines")?
+                .as_array()
+                .with_context(|| "invalid extent lines")?
+                .iter()
+                .map(|line| {
+                    let line = line.as_array().with_context(|| "invalid extent line")?;
+
+                    Ok((
+                        line[0].as_u64().with_context(|| "invalid extent start")?,
+                        line[1].as_u64().with_context(|| "invalid extent end")?,
+                    ))
+                })
+                .collect::<Result<Vec<_>>>()?,
+        })
+    }
+}
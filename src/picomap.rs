@@ -2,6 +2,10 @@ use crate::highlighter::*;
 use bitflags::bitflags;
 use std::cmp::{max, min};
 use std::fmt;
+use std::ops::Range;
+
+/// digits of the highlight ids hidden after the visible cells of a row
+pub const ID_WIDTH: usize = 3;
 
 bitflags! {
     struct Block: u8 {
@@ -139,11 +143,50 @@ impl Frame {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Selection {
+    Char,
+    Line,
+    Block,
+}
+
+impl Selection {
+    /// selection kind of a `mode(1)` string and whether an operator is pending
+    pub fn from_mode(mode: &str) -> Option<(Self, bool)> {
+        if let Some(forced) = mode.strip_prefix("no") {
+            return Some((
+                match forced {
+                    "V" => Selection::Line,
+                    "\x16" => Selection::Block,
+                    _ => Selection::Char,
+                },
+                true,
+            ));
+        }
+
+        match mode.chars().next()? {
+            'v' | 's' => Some((Selection::Char, false)),
+            'V' | 'S' => Some((Selection::Line, false)),
+            '\x16' | '\x13' => Some((Selection::Block, false)),
+            _ => None,
+        }
+    }
+
+    fn to_char(self) -> char {
+        match self {
+            Selection::Char => 's',
+            Selection::Line => 'l',
+            Selection::Block => 'b',
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
 pub struct Modifier {
     pub cursor: u64,
     pub visible_frame: Frame,
     pub select_frame: Option<Frame>,
+    pub selection: Selection,
 }
 
 impl Default for Modifier {
@@ -152,6 +195,7 @@ impl Default for Modifier {
             cursor: 0,
             visible_frame: Frame::default(),
             select_frame: None,
+            selection: Selection::Char,
         }
     }
 }
@@ -162,6 +206,7 @@ impl Modifier {
             cursor,
             visible_frame,
             select_frame: None,
+            selection: Selection::Char,
         }
     }
 
@@ -175,7 +220,7 @@ impl Modifier {
 
         if let Some(frame) = &self.select_frame {
             if frame.contains(offset, scale) {
-                return 's';
+                return self.selection.to_char();
             }
         }
 
@@ -187,29 +232,156 @@ impl Modifier {
     }
 }
 
+/// closed folds of a window as 0-based inclusive line ranges,
+/// each folded range is drawn as a single display line
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Folds {
+    ranges: Vec<(usize, usize)>,
+}
+
+impl Folds {
+    pub fn new(mut ranges: Vec<(usize, usize)>) -> Self {
+        ranges.sort_unstable();
+
+        Folds { ranges }
+    }
+
+    /// display line of the buffer line `i`
+    pub fn display(&self, i: usize) -> usize {
+        let mut hidden = 0;
+
+        for &(start, end) in &self.ranges {
+            if i <= end {
+                if i >= start {
+                    return start - hidden;
+                }
+
+                break;
+            }
+
+            hidden += end - start;
+        }
+
+        i - hidden
+    }
+
+    /// number of display lines of a buffer of `len` lines
+    pub fn display_len(&self, len: usize) -> usize {
+        self.display(len)
+    }
+
+    /// first buffer line of the display line `i`
+    fn buffer(&self, i: usize) -> usize {
+        let mut line = i;
+
+        for &(start, end) in &self.ranges {
+            if start >= line {
+                break;
+            }
+
+            line += end - start;
+        }
+
+        line
+    }
+
+    /// buffer lines drawn on the display lines `lines`
+    pub fn lines(&self, lines: Range<usize>) -> Range<usize> {
+        self.buffer(lines.start)..self.buffer(lines.end)
+    }
+
+    /// merge the highlights of each folded range into its display line, the larger id wins
+    pub fn collapse(&self, highlights: Highlights) -> Highlights {
+        if self.ranges.is_empty() {
+            return highlights;
+        }
+
+        let len = highlights.len();
+        let mut result = Vec::with_capacity(len);
+        let mut i = 0;
+
+        for &(start, end) in &self.ranges {
+            if start >= len {
+                break;
+            }
+
+            result.extend_from_slice(&highlights[i..start]);
+            result.push(
+                highlights[start..=min(end, len - 1)]
+                    .iter()
+                    .copied()
+                    .max()
+                    .unwrap_or(0),
+            );
+
+            i = end + 1;
+        }
+
+        if i < len {
+            result.extend_from_slice(&highlights[i..]);
+        }
+
+        result
+    }
+
+    /// display lines of the folds in a buffer of `len` lines
+    pub fn markers(&self, len: usize) -> Highlights {
+        let mut result = vec![0; self.display_len(len)];
+
+        for &(start, _) in &self.ranges {
+            if start < len {
+                result[self.display(start)] = 1;
+            }
+        }
+
+        result
+    }
+}
+
 pub struct Picomap {
-    pub changes: Highlights,
-    pub diags: Highlights,
+    pub columns: Vec<Highlights>,
     pub modifier: Modifier,
 }
 
 impl Default for Picomap {
     fn default() -> Self {
         Picomap {
-            changes: Highlights::default(),
-            diags: Highlights::default(),
+            columns: Vec::default(),
             modifier: Modifier::default(),
         }
     }
 }
 
 impl Picomap {
-    pub fn new(changes: Highlights, diags: Highlights, modifier: Modifier) -> Self {
-        Picomap {
-            changes,
-            diags,
-            modifier,
+    pub fn new(columns: Vec<Highlights>, modifier: Modifier) -> Self {
+        Picomap { columns, modifier }
+    }
+
+    /// stack the highlights of each layer into `width` columns of `len` lines.
+    /// layers are given as their column and highlight ids, and the larger id wins.
+    pub fn compose(width: usize, len: usize, layers: Vec<(usize, Highlights)>) -> Vec<Highlights> {
+        let mut columns = vec![vec![0; len]; width];
+
+        for (column, highlights) in layers {
+            if column >= width {
+                continue;
+            }
+
+            for (value, highlight) in columns[column].iter_mut().zip(highlights) {
+                *value = max(*value, highlight);
+            }
         }
+
+        columns
+    }
+
+    /// buffer lines drawn on the map row `i`
+    pub fn lines(i: u64, len: usize, height: u64) -> Range<usize> {
+        let scale = len as f64 / height as f64;
+        let start = ((i as f64) * scale) as usize;
+        let end = ((i + 1) as f64 * scale) as usize;
+
+        start..max(end, start + 1).min(len)
     }
 
     pub fn to_strings(&self, len: usize, height: u64) -> Vec<String> {
@@ -219,21 +391,26 @@ impl Picomap {
             return vec![];
         }
 
-        let change_line = Line::new(&self.changes).scale(height as usize);
-        let diag_line = Line::new(&self.diags).scale(height as usize);
+        let lines = self
+            .columns
+            .iter()
+            .map(|column| Line::new(column).scale(height as usize))
+            .collect::<Vec<_>>();
 
         for i in 0..height {
-            let change = change_line[i as usize];
-            let diag = diag_line[i as usize];
-
-            result.push(format!(
-                "{}{}{:>02}{:>02}{}",
-                change.0,
-                diag.0,
-                change.1,
-                diag.1,
-                self.modifier.to_char(i, len, height),
-            ));
+            let mut row = String::new();
+
+            for line in &lines {
+                row.push_str(&line[i as usize].0.to_string());
+            }
+
+            for line in &lines {
+                row.push_str(&format!("{:>0w$}", line[i as usize].1, w = ID_WIDTH));
+            }
+
+            row.push(self.modifier.to_char(i, len, height));
+
+            result.push(row);
         }
 
         result
@@ -244,6 +421,46 @@ impl Picomap {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_selection_from_mode() {
+        assert_eq!(Selection::from_mode("n"), None);
+        assert_eq!(Selection::from_mode("i"), None);
+        assert_eq!(Selection::from_mode("v"), Some((Selection::Char, false)));
+        assert_eq!(Selection::from_mode("V"), Some((Selection::Line, false)));
+        assert_eq!(
+            Selection::from_mode("\x16"),
+            Some((Selection::Block, false))
+        );
+        assert_eq!(Selection::from_mode("s"), Some((Selection::Char, false)));
+        assert_eq!(Selection::from_mode("S"), Some((Selection::Line, false)));
+        assert_eq!(
+            Selection::from_mode("\x13"),
+            Some((Selection::Block, false))
+        );
+        assert_eq!(Selection::from_mode("no"), Some((Selection::Char, true)));
+        assert_eq!(Selection::from_mode("noV"), Some((Selection::Line, true)));
+        assert_eq!(
+            Selection::from_mode("no\x16"),
+            Some((Selection::Block, true))
+        );
+    }
+
+    #[test]
+    fn test_picomap_format_selection() {
+        let modifier = Modifier {
+            select_frame: Some(Frame { top: 2, bottom: 1 }),
+            selection: Selection::Block,
+            ..Modifier::default()
+        };
+
+        let picomap = Picomap::new(vec![vec![0, 0, 0], vec![0, 0, 0]], modifier);
+
+        assert_eq!(
+            picomap.to_strings(3, 3),
+            vec!["  000000c", "  000000b", "  000000b",]
+        );
+    }
+
     #[test]
     fn test_picomap_format() {
         let len = 3;
@@ -252,11 +469,11 @@ mod tests {
         let diags = vec![0, 0, 0];
         let modifier = Modifier::default();
 
-        let picomap = Picomap::new(changes, diags, modifier);
+        let picomap = Picomap::new(vec![changes, diags], modifier);
 
         assert_eq!(
             picomap.to_strings(len, height),
-            vec!["▌ 0100c", "▌ 0200 ", "▌ 0300 ",]
+            vec!["▌ 001000c", "▌ 002000 ", "▌ 003000 ",]
         );
     }
 
@@ -268,21 +485,21 @@ mod tests {
         let diags = vec![0, 0, 0];
         let modifier = Modifier::default();
 
-        let picomap = Picomap::new(changes, diags, modifier);
+        let picomap = Picomap::new(vec![changes, diags], modifier);
 
         assert_eq!(
             picomap.to_strings(len, height),
             vec![
-                "▌ 0100c",
-                "▌ 0100c",
-                "▌ 0100c",
-                "▌ 0100c",
-                "▌ 0200 ",
-                "▌ 0200 ",
-                "▌ 0200 ",
-                "▌ 0300 ",
-                "▌ 0300 ",
-                "▌ 0300 ",
+                "▌ 001000c",
+                "▌ 001000c",
+                "▌ 001000c",
+                "▌ 001000c",
+                "▌ 002000 ",
+                "▌ 002000 ",
+                "▌ 002000 ",
+                "▌ 003000 ",
+                "▌ 003000 ",
+                "▌ 003000 ",
             ]
         );
     }
@@ -295,11 +512,59 @@ mod tests {
         let diags = vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
         let modifier = Modifier::default();
 
-        let picomap = Picomap::new(changes, diags, modifier);
+        let picomap = Picomap::new(vec![changes, diags], modifier);
 
         assert_eq!(
             picomap.to_strings(len, height),
-            vec!["▖ 0100c", "▌ 0300 ", "▌ 0500 ", "▌ 0700 ", "▌ 0900 ",]
+            vec![
+                "▖ 001000c",
+                "▌ 003000 ",
+                "▌ 005000 ",
+                "▌ 007000 ",
+                "▌ 009000 ",
+            ]
         );
     }
+
+    #[test]
+    fn test_picomap_compose() {
+        let columns = Picomap::compose(
+            2,
+            3,
+            vec![
+                (0, vec![1, 0, 0]),
+                (1, vec![0, 2, 0]),
+                (1, vec![0, 3, 4]),
+                (2, vec![5, 5, 5]),
+            ],
+        );
+
+        assert_eq!(columns, vec![vec![1, 0, 0], vec![0, 3, 4]]);
+    }
+
+    #[test]
+    fn test_folds() {
+        let folds = Folds::new(vec![(6, 7), (2, 4)]);
+
+        assert_eq!(folds.display(1), 1);
+        assert_eq!(folds.display(3), 2);
+        assert_eq!(folds.display(5), 3);
+        assert_eq!(folds.display(7), 4);
+        assert_eq!(folds.display_len(10), 7);
+        assert_eq!(folds.lines(2..4), 2..6);
+        assert_eq!(folds.lines(4..7), 6..10);
+        assert_eq!(
+            folds.collapse(vec![1, 0, 0, 3, 2, 0, 0, 4, 5, 0]),
+            vec![1, 0, 3, 0, 4, 5, 0]
+        );
+        assert_eq!(folds.markers(10), vec![0, 0, 1, 0, 1, 0, 0]);
+    }
+
+    #[test]
+    fn test_picomap_lines() {
+        assert_eq!(Picomap::lines(0, 10, 5), 0..2);
+        assert_eq!(Picomap::lines(4, 10, 5), 8..10);
+        assert_eq!(Picomap::lines(4, 3, 10), 1..2);
+        assert_eq!(Picomap::lines(9, 3, 10), 2..3);
+    }
 }
@@ -0,0 +1,164 @@
+use crate::watch::same_file;
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+
+/// samples of each source line in a profile
+#[derive(Debug, Default)]
+pub struct Profile {
+    files: BTreeMap<String, BTreeMap<u64, f64>>,
+}
+
+impl Profile {
+    /// parse `pprof -lines -text` output, or folded stacks whose frames carry `file:line`.
+    /// the samples of a stack are given to its innermost frame with a source line.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut profile = Profile::default();
+
+        let pprof = text
+            .lines()
+            .any(|line| line.trim_start().starts_with("flat") && line.contains("flat%"));
+
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() {
+                continue;
+            }
+
+            if pprof {
+                // e.g. `1.20s 40.00% 40.00% 1.50s 50.00%  main.foo /src/main.go:42`
+                let amount = match line.split_whitespace().next().and_then(parse_amount) {
+                    Some(amount) => amount,
+                    None => continue,
+                };
+
+                if let Some((path, lnum)) = line.split_whitespace().last().and_then(location) {
+                    profile.add(path, lnum, amount);
+                }
+            } else {
+                // e.g. `main (src/main.rs:10);foo (src/foo.rs:3) 12`
+                let split = line
+                    .rfind(' ')
+                    .with_context(|| format!("invalid folded stack at line {}: {}", n + 1, line))?;
+
+                let samples = line[split + 1..]
+                    .parse::<f64>()
+                    .with_context(|| format!("invalid sample count at line {}: {}", n + 1, line))?;
+
+                if let Some((path, lnum)) = line[..split].rsplit(';').find_map(location) {
+                    profile.add(path, lnum, samples);
+                }
+            }
+        }
+
+        Ok(profile)
+    }
+
+    fn add(&mut self, path: &str, lnum: u64, samples: f64) {
+        *self
+            .files
+            .entry(path.to_string())
+            .or_default()
+            .entry(lnum)
+            .or_default() += samples;
+    }
+
+    /// samples of the 1-based lines of the file `name`
+    pub fn lines(&self, name: &str) -> Vec<(u64, f64)> {
+        self.files
+            .iter()
+            .find(|(path, _)| same_file(name, path))
+            .map(|(_, lines)| {
+                lines
+                    .iter()
+                    .map(|(lnum, samples)| (*lnum, *samples))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// the first `path:line` in a frame, e.g. `foo (/src/foo.rs:12:5)`
+fn location(frame: &str) -> Option<(&str, u64)> {
+    frame
+        .match_indices(':')
+        .filter_map(|(i, _)| {
+            let digits = frame[i + 1..]
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(frame.len() - i - 1);
+            let lnum = frame[i + 1..i + 1 + digits].parse().ok()?;
+
+            let start = frame[..i]
+                .rfind(|c: char| c.is_whitespace() || c == '(' || c == '[')
+                .map_or(0, |start| start + 1);
+            let path = &frame[start..i];
+
+            if path.is_empty() {
+                return None;
+            }
+
+            Some((path, lnum))
+        })
+        .next()
+}
+
+/// a pprof amount like `1.20s`, `350ms` or `12MB`, in the base unit
+fn parse_amount(value: &str) -> Option<f64> {
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+
+    let scale = match &value[split..] {
+        "" | "s" | "B" => 1.0,
+        "ns" => 1e-9,
+        "us" | "µs" => 1e-6,
+        "ms" => 1e-3,
+        "min" => 60.0,
+        "h" => 3600.0,
+        "kB" => 1e3,
+        "MB" => 1e6,
+        "GB" => 1e9,
+        "TB" => 1e12,
+        _ => return None,
+    };
+
+    value[..split]
+        .parse::<f64>()
+        .ok()
+        .map(|amount| amount * scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_parse_folded() {
+        let profile = Profile::parse(
+            "main (src/main.rs:10);foo (src/foo.rs:3) 12
+main (src/main.rs:10);foo (src/foo.rs:3);libc 8
+main (src/main.rs:11) 5
+",
+        )
+        .unwrap();
+
+        assert_eq!(profile.lines("/project/src/foo.rs"), vec![(3, 20.0)]);
+        assert_eq!(profile.lines("/project/src/main.rs"), vec![(11, 5.0)]);
+
+        assert!(Profile::parse("main foo\n").is_err());
+    }
+
+    #[test]
+    fn test_profile_parse_pprof() {
+        let profile = Profile::parse(
+            "Showing nodes accounting for 3s, 100% of 3s total
+      flat  flat%   sum%        cum   cum%
+     1.20s 40.00% 40.00%      1.50s 50.00%  main.foo /src/main.go:42
+     300ms 10.00% 50.00%      300ms 10.00%  main.bar /src/main.go:7
+",
+        )
+        .unwrap();
+
+        assert_eq!(profile.lines("/src/main.go"), vec![(7, 0.3), (42, 1.2)]);
+    }
+}
@@ -0,0 +1,21 @@
+use regex::Regex;
+
+/// lines drawn by a rule layer, matched by a regex in buffers of the given filetypes
+#[derive(Debug, Clone)]
+pub struct RegexRule {
+    pub regex: Regex,
+    /// filetypes the rule applies to, or every filetype when empty
+    pub filetypes: Vec<String>,
+}
+
+impl PartialEq for RegexRule {
+    fn eq(&self, other: &Self) -> bool {
+        self.regex.as_str() == other.regex.as_str() && self.filetypes == other.filetypes
+    }
+}
+
+impl RegexRule {
+    pub fn applies(&self, filetype: &str) -> bool {
+        self.filetypes.is_empty() || self.filetypes.iter().any(|v| v == filetype)
+    }
+}
@@ -1,13 +1,30 @@
+use crate::blame::{blame_cached, Blame, Commit};
+use crate::cache::*;
+use crate::config::*;
+use crate::coverage::*;
+use crate::extmarks::EXTMARKS;
 use crate::highlighter::*;
+use crate::junit::{TestFailure, TestResults};
+use crate::lsp;
 use crate::message::*;
 use crate::picomap::*;
+use crate::profile::Profile;
+use crate::rules::RegexRule;
+use crate::signs::EXTMARK_SIGNS;
+use crate::spell::SPELL_CHECK;
+use crate::tags::{tag_kind, Tags};
+use crate::watch::Watched;
 use anyhow::{Context, Result};
 use async_trait::async_trait;
 use neovim_lib::neovim_api::{Buffer, Window};
 use neovim_lib::{Neovim, NeovimApi, Session, Value};
+use regex::Regex;
+use std::cmp::{max, min};
+use std::collections::HashSet;
 use std::convert::TryFrom;
-use std::sync::mpsc;
-use std::time::Duration;
+use std::sync::{mpsc, Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 #[async_trait]
 pub trait ServerTrait {
@@ -18,10 +35,127 @@ pub struct Server {
     nvim: Neovim,
     buf: Option<Buffer>,
     win: Option<Window>,
+    hover_buf: Option<Buffer>,
+    hover_win: Option<Window>,
+    config: Config,
+    /// what is done with the map to dodge the cursor or text, `Dodge::None` while not dodging
+    dodging: Dodge,
+    excluded: bool,
+    hidden: bool,
     picomap: Picomap,
+    /// closed folds of the current window, empty unless mapping display lines
+    folds: Folds,
+    states: Cache<i64, BufferState>,
+    quickfix: Vec<Location>,
+    coverage: Watched<CoverageReport>,
+    profile: Watched<Profile>,
+    test_results: Watched<TestResults>,
+    tags: Watched<Tags>,
+    /// results of `git blame` keyed by the blob id of the blamed content
+    blames: Arc<Mutex<Cache<String, Blame>>>,
+    /// buffers blamed on another thread, which sends the blamed lines to `blamed`
+    blaming: HashSet<i64>,
+    blamed: (mpsc::Sender<BlameResult>, mpsc::Receiver<BlameResult>),
+    /// id of the channel to neovim, to which lua callbacks send their results
+    channel: u64,
+}
+
+/// blamed lines of a buffer sent from the thread running git
+type BlameResult = (i64, Result<Vec<(u64, Commit)>>);
+
+/// highlighters of a buffer, kept while switching to other buffers
+#[derive(Default)]
+struct BufferState {
     diags: DiagnosticsHighlighter,
+    tests: DiagnosticsHighlighter,
     changes: ChangeHighlighter,
-    buf_len: usize,
+    marks: MarksHighlighter,
+    occurrence: OccurrenceHighlighter,
+    history: HistoryHighlighter,
+    annotation: AnnotationHighlighter,
+    conflict: ConflictHighlighter,
+    recent: RecentHighlighter,
+    coverage: CoverageHighlighter,
+    profile: ProfileHighlighter,
+    outline: OutlineHighlighter,
+    blame: BlameHighlighter,
+    quickfix: QuickfixHighlighter,
+    spell: SpellHighlighter,
+    format: FormatHighlighter,
+    /// highlighters of the sign layers, one for each sign rule
+    signs: Vec<SignHighlighter>,
+    /// highlighters of the extmark layers, one for each extmark rule
+    extmarks: Vec<ExtmarkHighlighter>,
+    /// highlighters of the regex layers, one for each regex rule
+    rules: Vec<RuleHighlighter>,
+    len: usize,
+    /// lines followed by `nvim_buf_attach()` while attached
+    lines: Vec<String>,
+    attached: bool,
+    /// changedtick and word the occurrences were last requested with
+    occurrence_key: Option<(i64, String)>,
+    /// changedtick the language servers answered symbols at
+    outline_tick: Option<i64>,
+    /// `spelllang` the lines were checked with, empty while `spell` is off
+    spelllang: String,
+    /// filetype the rules were matched with
+    filetype: String,
+}
+
+impl BufferState {
+    /// highlighter of the layer named `name`
+    fn layer(&self, name: &str) -> Option<&dyn Highlighter> {
+        match name {
+            "outline" => Some(&self.outline),
+            "blame" => Some(&self.blame),
+            "change" => Some(&self.changes),
+            "marks" => Some(&self.marks),
+            "occurrence" => Some(&self.occurrence),
+            "history" => Some(&self.history),
+            "annotation" => Some(&self.annotation),
+            "conflict" => Some(&self.conflict),
+            "recent" => Some(&self.recent),
+            "coverage" => Some(&self.coverage),
+            "profile" => Some(&self.profile),
+            "diag" => Some(&self.diags),
+            "tests" => Some(&self.tests),
+            "quickfix" => Some(&self.quickfix),
+            "spell" => Some(&self.spell),
+            "format" => Some(&self.format),
+            name => match rule_index(name, "sign") {
+                Some(i) => self
+                    .signs
+                    .get(i)
+                    .map(|highlighter| highlighter as &dyn Highlighter),
+                None => match rule_index(name, "extmark") {
+                    Some(i) => self
+                        .extmarks
+                        .get(i)
+                        .map(|highlighter| highlighter as &dyn Highlighter),
+                    None => rule_index(name, "rule")
+                        .and_then(|i| self.rules.get(i))
+                        .map(|highlighter| highlighter as &dyn Highlighter),
+                },
+            },
+        }
+    }
+
+    /// replace the followed lines with `lines` and rescan them
+    fn reset_lines(&mut self, lines: Vec<String>, keywords: &[String]) {
+        self.annotation.sync(&lines, keywords);
+        self.conflict.sync(&lines);
+        self.recent.reset(lines.len());
+        self.lines = lines;
+    }
+
+    /// match all the lines against the rules which apply to the filetype
+    fn sync_rules(&mut self, rules: &[RegexRule]) {
+        self.rules.resize_with(rules.len(), Default::default);
+
+        for (highlighter, rule) in self.rules.iter_mut().zip(rules) {
+            highlighter.sync(&self.lines, to_regex(rule, &self.filetype));
+        }
+    }
 }
 
 impl Default for Server {
@@ -32,17 +166,33 @@ impl Default for Server {
             nvim: Neovim::new(session.expect("session not found")),
             buf: None,
             win: None,
+            hover_buf: None,
+            hover_win: None,
+            config: Config::default(),
+            dodging: Dodge::None,
+            excluded: false,
+            hidden: false,
             picomap: Picomap::default(),
-            diags: DiagnosticsHighlighter::default(),
-            changes: ChangeHighlighter::default(),
-            buf_len: 0,
+            folds: Folds::default(),
+            states: Cache::new(Config::default().max_buffers as usize),
+            quickfix: vec![],
+            coverage: Watched::default(),
+            profile: Watched::default(),
+            test_results: Watched::default(),
+            tags: Watched::default(),
+            blames: Arc::new(Mutex::new(Cache::new(
+                Config::default().max_buffers as usize,
+            ))),
+            blaming: HashSet::new(),
+            blamed: mpsc::channel(),
+            channel: 0,
         }
     }
 }
 
 fn to_diagnostic(loc: &Location) -> Diagnostic {
     Diagnostic {
-        i: loc.lnum as usize,
+        i: (loc.lnum as usize).saturating_sub(1),
         text: loc.text.to_string(),
         level: match loc.typ {
             LocationType::Warning => DiagnosticLevel::Warning,
@@ -50,94 +200,1294 @@ fn to_diagnostic(loc: &Location) -> Diagnostic {
             _ => DiagnosticLevel::None,
         },
     }
-}
+}
+
+fn to_quickfix_item(loc: &Location) -> QuickfixItem {
+    QuickfixItem {
+        i: (loc.lnum as usize).saturating_sub(1),
+        text: loc.text.to_string(),
+        kind: match loc.typ {
+            LocationType::Warning => QuickfixKind::Warning,
+            LocationType::Error => QuickfixKind::Error,
+            _ => QuickfixKind::Entry,
+        },
+    }
+}
+
+fn to_mark(entry: &MarkEntry) -> Mark {
+    Mark {
+        i: (entry.lnum as usize).saturating_sub(1),
+        name: entry.name,
+    }
+}
+
+fn to_occurrence(highlight: &DocumentHighlight) -> Occurrence {
+    Occurrence {
+        start: highlight.start as usize,
+        end: highlight.end as usize,
+        kind: OccurrenceKind::from(highlight.kind),
+    }
+}
+
+fn to_coverage_item(line: &(u64, LineCoverage)) -> CoverageItem {
+    CoverageItem {
+        i: (line.0 as usize).saturating_sub(1),
+        kind: match line.1 {
+            LineCoverage::Partial => CoverageKind::Partial,
+            LineCoverage::Uncovered => CoverageKind::Uncovered,
+            LineCoverage::Covered => CoverageKind::None,
+        },
+    }
+}
+
+fn to_test_diagnostic(failure: &TestFailure) -> Diagnostic {
+    Diagnostic {
+        i: (failure.lnum as usize).saturating_sub(1),
+        text: format!("test {}: {}", failure.name, failure.message),
+        level: if failure.error {
+            DiagnosticLevel::Warning
+        } else {
+            DiagnosticLevel::Danger
+        },
+    }
+}
+
+fn to_symbol(symbol: &DocumentSymbol) -> Option<Symbol> {
+    Some(Symbol {
+        start: symbol.start as usize,
+        end: symbol.end as usize,
+        kind: lsp::symbol_kind(symbol.kind)?.to_string(),
+        name: symbol.name.to_string(),
+    })
+}
+
+/// regex of `rule` when it applies to `filetype`
+fn to_regex<'a>(rule: &'a RegexRule, filetype: &str) -> Option<&'a Regex> {
+    if rule.applies(filetype) {
+        Some(&rule.regex)
+    } else {
+        None
+    }
+}
+
+fn to_spell_item(word: &BadWord) -> SpellItem {
+    SpellItem {
+        word: word.word.clone(),
+        bad: word.kind == "bad",
+    }
+}
+
+fn to_change(hunk: &Hunk) -> Change {
+    Change {
+        i: hunk.lnum as usize - 1,
+        len: hunk.len,
+    }
+}
+
+#[async_trait]
+impl ServerTrait for Server {
+    async fn start(&mut self, done: mpsc::Receiver<()>) -> Result<()> {
+        let recv = self.nvim.session.start_event_loop_channel();
+
+        eprintln!("start event loop");
+
+        self.buf = Some(
+            self.nvim
+                .create_buf(false, true)
+                .context("failed to create buf")?,
+        );
+
+        self.channel = self.get_channel()?;
+
+        self.config = self.load_config().unwrap_or_else(|err| {
+            eprintln!("err: {:#}", err);
+            Config::default()
+        });
+        self.states.set_capacity(self.config.max_buffers as usize);
+        self.blames
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .set_capacity(self.config.max_buffers as usize);
+
+        loop {
+            if Err(mpsc::TryRecvError::Empty) != done.try_recv() {
+                break;
+            }
+
+            match recv.try_recv() {
+                Err(mpsc::TryRecvError::Empty) => {}
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    eprintln!("neovim has disconeccted");
+                    break;
+                }
+                Ok((event, values)) => {
+                    let result = match Message::from(event) {
+                        Message::Sync => self.sync(values).context("failed to call sync handler"),
+                        Message::Show => self.show(values).context("failed to call show handler"),
+                        Message::Resize => {
+                            self.resize(values).context("failed to call resize handler")
+                        }
+                        Message::Close => {
+                            self.close(values).context("failed to call close handler")
+                        }
+                        Message::Configure => self
+                            .configure(values)
+                            .context("failed to call configure handler"),
+                        Message::Evict => {
+                            self.evict(values).context("failed to call evict handler")
+                        }
+                        Message::Quickfix => self
+                            .quickfix(values)
+                            .context("failed to call quickfix handler"),
+                        Message::Hover => {
+                            self.hover(values).context("failed to call hover handler")
+                        }
+                        Message::Unhover => self
+                            .unhover(values)
+                            .context("failed to call unhover handler"),
+                        Message::Occurrence => self
+                            .occurrence(values)
+                            .context("failed to call occurrence handler"),
+                        Message::DocumentHighlight => self
+                            .document_highlight(values)
+                            .context("failed to call document_highlight handler"),
+                        Message::JumpConflict => self
+                            .jump_conflict(values)
+                            .context("failed to call jump conflict handler"),
+                        Message::Outline => self
+                            .outline(values)
+                            .context("failed to call outline handler"),
+                        Message::DocumentSymbol => self
+                            .document_symbol(values)
+                            .context("failed to call document_symbol handler"),
+                        Message::Blame => {
+                            self.blame(values).context("failed to call blame handler")
+                        }
+                        Message::BufLines => self
+                            .buf_lines(values)
+                            .context("failed to call buf lines handler"),
+                        Message::BufChangedTick => Ok(()),
+                        Message::BufDetach => self
+                            .buf_detach(values)
+                            .context("failed to call buf detach handler"),
+                        _ => {
+                            eprintln!("unknown message");
+                            Ok(())
+                        }
+                    };
+                    if let Err(err) = result {
+                        eprintln!("err: {}", err);
+                    }
+                }
+            }
+
+            while let Ok((key, lines)) = self.blamed.1.try_recv() {
+                if let Err(err) = self.apply_blame(key, lines) {
+                    eprintln!("err: {:#}", err);
+                }
+            }
+
+            smol::Timer::new(Duration::from_millis(10)).await;
+        }
+
+        eprintln!("exit event loop");
+
+        Ok(())
+    }
+}
+
+impl Server {
+    fn sync(&mut self, values: Vec<Value>) -> Result<()> {
+        let payload = SyncPayload::try_from(values).context("invalid payload")?;
+
+        // entries without a line belong to the whole file
+        let diags = payload
+            .locations
+            .iter()
+            .filter(|loc| loc.lnum > 0)
+            .map(to_diagnostic)
+            .collect();
+        let changes = payload.hunks.iter().map(to_change).collect();
+
+        let cur_buf = self
+            .nvim
+            .get_current_buf()
+            .context("failed to get current buffer")?;
+
+        let buf_len = cur_buf
+            .line_count(&mut self.nvim)
+            .context("failed to get line count")? as usize;
+
+        let key = cur_buf
+            .get_number(&mut self.nvim)
+            .context("failed to get buffer number")?;
+
+        let name = cur_buf
+            .get_name(&mut self.nvim)
+            .context("failed to get buffer name")?;
+
+        let filetype = cur_buf
+            .get_option(&mut self.nvim, "filetype")
+            .context("failed to get filetype option")?
+            .as_str()
+            .context("invalid filetype")?
+            .to_owned();
+
+        // a buffer evicted from the cache is still attached and not sent again,
+        // so the lines of a new state are read here
+        let fresh = !self.states.contains(&key);
+
+        if fresh {
+            let lines = cur_buf
+                .get_lines(&mut self.nvim, 0, -1, false)
+                .context("failed to get buffer lines")?;
+
+            self.states
+                .get_or_insert_with(key, BufferState::default)
+                .reset_lines(lines, &self.config.annotations);
+        }
+
+        let quickfix = self.quickfix_items(key);
+        let marks = self.get_marks(key)?;
+        let (jump_lines, change_lines) = if self.config.is_enabled("history") {
+            (
+                self.get_positions("getjumplist", vec![], key)?,
+                self.get_positions("getchangelist", vec![Value::from(key)], key)?,
+            )
+        } else {
+            (vec![], vec![])
+        };
+        // reports of disabled layers are neither resolved nor read
+        let config = &self.config;
+        let report = |name: &str, path: &str| {
+            if config.is_enabled(name) {
+                path.to_owned()
+            } else {
+                String::new()
+            }
+        };
+        let paths = vec![
+            report("coverage", &config.coverage),
+            report("profile", &config.profile),
+            report("tests", &config.test_results),
+        ];
+        let paths = self.report_paths(paths)?;
+        let coverage = self.coverage_items(&paths[0], &name);
+        let profile = self.profile_items(&paths[1], &name);
+        let tests = self.test_diagnostics(&paths[2], &name);
+        let signs = self.get_signs(key)?;
+        let extmarks = self.get_extmarks()?;
+        // the layer is cleared while disabled as while `spell` is off
+        let spelllang = if self.config.is_enabled("spell") {
+            self.nvim
+                .eval("&spell ? &spelllang : ''")
+                .context("failed to eval spelllang")?
+                .as_str()
+                .context("invalid spelllang")?
+                .to_owned()
+        } else {
+            String::new()
+        };
+        let spell = self.rescan_spell(key, &spelllang)?;
+        // the options are only read while the layer is drawn
+        let text_limit = if self.config.is_enabled("format") {
+            Some(self.get_text_limit()?)
+        } else {
+            None
+        };
+        let levels = self
+            .config
+            .layers
+            .iter()
+            .find(|layer| layer.name == "profile")
+            .map_or(1, |layer| layer.colors.len() as u64);
+
+        let history_levels = self
+            .config
+            .layers
+            .iter()
+            .find(|layer| layer.name == "history")
+            .map_or(1, |layer| layer.colors.len() as u64);
+        let recent_levels = self
+            .config
+            .layers
+            .iter()
+            .find(|layer| layer.name == "recent")
+            .map_or(1, |layer| layer.colors.len() as u64);
+
+        let state = self.states.get_or_insert_with(key, BufferState::default);
+
+        state.diags.sync(buf_len, diags);
+        state.tests.sync(buf_len, tests);
+        state.changes.sync(buf_len, changes);
+        state.quickfix.sync(buf_len, quickfix);
+        state.marks.sync(buf_len, marks);
+        state
+            .history
+            .sync(buf_len, jump_lines, change_lines, history_levels);
+        state.coverage.sync(buf_len, coverage);
+        state.profile.sync(buf_len, profile, levels);
+        state
+            .signs
+            .resize_with(self.config.signs.len(), Default::default);
+
+        for (highlighter, rule) in state.signs.iter_mut().zip(&self.config.signs) {
+            let items = signs
+                .iter()
+                .filter(|sign| rule.matches(sign))
+                .map(|sign| SignItem {
+                    i: (sign.lnum as usize).saturating_sub(1),
+                    name: sign.name.clone(),
+                })
+                .collect();
+
+            highlighter.sync(buf_len, items);
+        }
+
+        state
+            .extmarks
+            .resize_with(self.config.extmarks.len(), Default::default);
+
+        for (highlighter, rule) in state.extmarks.iter_mut().zip(&self.config.extmarks) {
+            let items = extmarks
+                .iter()
+                .filter(|extmark| rule.matches(extmark))
+                .map(|extmark| ExtmarkItem {
+                    start: extmark.start as usize,
+                    end: extmark.end as usize,
+                    hl_group: extmark.hl_group.clone(),
+                })
+                .collect();
+
+            highlighter.sync(buf_len, items);
+        }
+
+        if fresh || state.filetype != filetype {
+            state.filetype = filetype;
+            state.sync_rules(&self.config.rules);
+        }
+
+        if let Some(spell) = spell {
+            state.spell.sync(spell);
+            state.spelllang = spelllang;
+        }
+
+        let (limit, tabstop) = text_limit.unwrap_or_else(|| state.format.options());
+
+        if fresh || state.format.outdated(limit, tabstop) {
+            state.format.sync(&state.lines, limit, tabstop);
+        }
+
+        state.recent.sync(
+            buf_len,
+            Instant::now(),
+            Duration::from_secs(self.config.recent_decay),
+            recent_levels,
+        );
+        // unlike the other rules, the line count changes without entering another window
+        let min_lines = self.config.min_lines as usize;
+        let crossed = (state.len >= min_lines) != (buf_len >= min_lines);
+
+        state.len = buf_len;
+
+        if !state.attached {
+            state.attached = cur_buf
+                .attach(&mut self.nvim, true, vec![])
+                .context("failed to attach buffer")?;
+        }
+
+        if crossed {
+            self.exclude()?;
+        }
+
+        self.picomap.modifier = self.get_modifier()?;
+
+        self.dodge()?;
+
+        self.redraw()
+    }
+
+    fn quickfix(&mut self, values: Vec<Value>) -> Result<()> {
+        let payload = QuickfixPayload::try_from(values).context("invalid payload")?;
+
+        self.quickfix = payload.locations;
+
+        let key = self
+            .nvim
+            .get_current_buf()
+            .context("failed to get current buffer")?
+            .get_number(&mut self.nvim)
+            .context("failed to get buffer number")?;
+
+        let items = self.quickfix_items(key);
+
+        if let Some(state) = self.states.get_mut(&key) {
+            state.quickfix.sync(state.len, items);
+        }
+
+        self.redraw()
+    }
+
+    /// quickfix entries which belong to the buffer `bufnr`
+    fn quickfix_items(&self, bufnr: i64) -> Vec<QuickfixItem> {
+        self.quickfix
+            .iter()
+            .filter(|loc| loc.bufnr as i64 == bufnr && loc.lnum > 0)
+            .map(to_quickfix_item)
+            .collect()
+    }
+
+    /// follow the changed lines of an attached buffer and rescan them
+    fn buf_lines(&mut self, values: Vec<Value>) -> Result<()> {
+        let event = BufLinesEvent::try_from(values).context("invalid payload")?;
+
+        let buf = Buffer::new(event.buf.clone());
+
+        let key = buf
+            .get_number(&mut self.nvim)
+            .context("failed to get buffer number")?;
+
+        // only the changed lines are checked again
+        let spell = match self.states.get(&key) {
+            Some(state) if !state.spelllang.is_empty() && self.config.is_enabled("spell") => {
+                self.spell_check(key, &event.lines)?
+            }
+            _ => None,
+        };
+
+        let state = match self.states.get_mut(&key) {
+            Some(state) => state,
+            None => {
+                // the buffer has been evicted, so stop following it
+                buf.detach(&mut self.nvim)
+                    .context("failed to detach buffer")?;
+
+                return Ok(());
+            }
+        };
+
+        let len = state.lines.len();
+        let last = event.last.map_or(len, |last| min(last as usize, len));
+        let first = min(event.first as usize, last);
+
+        state
+            .annotation
+            .update(first, last, &event.lines, &self.config.annotations);
+
+        // the first event sends the whole buffer, which is not an edit
+        match event.last {
+            Some(_) => state
+                .recent
+                .update(first, last, event.lines.len(), Instant::now()),
+            None => state.recent.reset(event.lines.len()),
+        }
+
+        if let Some(spell) = spell {
+            state.spell.update(first, last, spell);
+        }
+
+        state.format.update(first, last, &event.lines);
+
+        for (highlighter, rule) in state.rules.iter_mut().zip(&self.config.rules) {
+            highlighter.update(first, last, &event.lines, to_regex(rule, &state.filetype));
+        }
+
+        let count = event.lines.len();
+
+        state.lines.splice(first..last, event.lines);
+        state.conflict.update(first, last, count, &state.lines);
+
+        Ok(())
+    }
+
+    fn buf_detach(&mut self, values: Vec<Value>) -> Result<()> {
+        let buf = Buffer::new(values.first().context("missing buffer")?.clone());
+
+        let key = buf
+            .get_number(&mut self.nvim)
+            .context("failed to get buffer number")?;
+
+        // attach again on the next sync, which sends the whole buffer
+        if let Some(state) = self.states.get_mut(&key) {
+            state.attached = false;
+        }
+
+        Ok(())
+    }
+
+    /// move the cursor to the next unresolved conflict,
+    /// or to the previous one when the direction is negative
+    fn jump_conflict(&mut self, values: Vec<Value>) -> Result<()> {
+        let forward = values
+            .first()
+            .and_then(|value| value.as_i64())
+            .context("invalid direction")?
+            >= 0;
+
+        let cur_win = self
+            .nvim
+            .get_current_win()
+            .context("failed to get current window")?;
+
+        let key = cur_win
+            .get_buf(&mut self.nvim)
+            .context("failed to get current buffer")?
+            .get_number(&mut self.nvim)
+            .context("failed to get buffer number")?;
+
+        let cursor = cur_win
+            .get_cursor(&mut self.nvim)
+            .context("failed to get cursor")?
+            .0 as usize;
+
+        let target = self.states.get(&key).and_then(|state| {
+            if forward {
+                state.conflict.next(cursor - 1)
+            } else {
+                state.conflict.prev(cursor - 1)
+            }
+        });
+
+        let i = match target {
+            Some(i) => i,
+            None => {
+                return self
+                    .nvim
+                    .command("echo 'no conflicts'")
+                    .context("failed to echo");
+            }
+        };
+
+        // keep the position in the jumplist to come back with <C-o>
+        self.nvim
+            .command("normal! m'")
+            .context("failed to set the previous context mark")?;
+
+        cur_win
+            .set_cursor(&mut self.nvim, (i as i64 + 1, 0))
+            .context("failed to set cursor")
+    }
+
+    /// draw the symbols of the current buffer given by the language servers,
+    /// or by the tags file when no server knows them
+    fn outline(&mut self, _values: Vec<Value>) -> Result<()> {
+        let cur_buf = self
+            .nvim
+            .get_current_buf()
+            .context("failed to get current buffer")?;
+
+        let key = cur_buf
+            .get_number(&mut self.nvim)
+            .context("failed to get buffer number")?;
+
+        let tick = cur_buf
+            .get_changedtick(&mut self.nvim)
+            .context("failed to get changedtick")?;
+
+        let len = match self.states.get(&key) {
+            Some(state) if state.outline_tick == Some(tick) => return Ok(()),
+            Some(state) => state.len,
+            None => return Ok(()),
+        };
+
+        // the symbols are drawn when the language servers answer
+        if self.request_document_symbols(key, tick)? {
+            return Ok(());
+        }
+
+        let name = cur_buf
+            .get_name(&mut self.nvim)
+            .context("failed to get buffer name")?;
+
+        let symbols = self.tag_symbols(&name, len)?;
+
+        // the tick is kept unset, so that a language server attached later is asked
+        if let Some(state) = self.states.get_mut(&key) {
+            state.outline.sync(state.len, symbols);
+        }
+
+        self.redraw()
+    }
+
+    /// draw the symbols answered by the language servers,
+    /// or the symbols in the tags file when there is none
+    fn document_symbol(&mut self, values: Vec<Value>) -> Result<()> {
+        let payload = DocumentSymbolPayload::try_from(values).context("invalid payload")?;
+
+        let len = match self.states.get(&payload.bufnr) {
+            // a response to a newer request has arrived first
+            Some(state) if state.outline_tick > Some(payload.tick) => return Ok(()),
+            Some(state) => state.len,
+            None => return Ok(()),
+        };
+
+        let mut symbols: Vec<_> = payload.symbols.iter().filter_map(to_symbol).collect();
+
+        // the servers may still be indexing, so they are asked again while answering nothing
+        let tick = if symbols.is_empty() {
+            symbols = self.tag_symbols(&payload.name, len)?;
+            None
+        } else {
+            Some(payload.tick)
+        };
+
+        if let Some(state) = self.states.get_mut(&payload.bufnr) {
+            state.outline.sync(state.len, symbols);
+            state.outline_tick = tick;
+        }
+
+        self.redraw()
+    }
+
+    /// blame the current file on another thread, unless it is being blamed.
+    /// the file is blamed again only when the content on disk has changed.
+    fn blame(&mut self, _values: Vec<Value>) -> Result<()> {
+        if !self.config.is_enabled("blame") {
+            return Ok(());
+        }
+
+        let cur_buf = self
+            .nvim
+            .get_current_buf()
+            .context("failed to get current buffer")?;
+
+        let key = cur_buf
+            .get_number(&mut self.nvim)
+            .context("failed to get buffer number")?;
+
+        let name = cur_buf
+            .get_name(&mut self.nvim)
+            .context("failed to get buffer name")?;
+
+        // terminals and buffers of other plugins are not files
+        let buftype = cur_buf
+            .get_option(&mut self.nvim, "buftype")
+            .context("failed to get buftype option")?;
+
+        if name.is_empty()
+            || buftype.as_str() != Some("")
+            || !self.states.contains(&key)
+            || !self.blaming.insert(key)
+        {
+            return Ok(());
+        }
+
+        let blames = Arc::clone(&self.blames);
+        let sender = self.blamed.0.clone();
+
+        thread::spawn(move || {
+            // the server may have exited in the meantime
+            let _ = sender.send((key, blame_cached(&name, &blames)));
+        });
+
+        Ok(())
+    }
+
+    /// grade the lines of the buffer `key` by the age of their last commit
+    fn apply_blame(&mut self, key: i64, lines: Result<Vec<(u64, Commit)>>) -> Result<()> {
+        self.blaming.remove(&key);
+
+        let lines = lines.context("failed to blame")?;
+
+        let levels = match self
+            .config
+            .layers
+            .iter()
+            .find(|layer| layer.name == "blame" && layer.enabled)
+        {
+            Some(layer) => layer.colors.len() as u64,
+            None => return Ok(()),
+        };
+
+        let items = lines
+            .into_iter()
+            .map(|(lnum, commit)| BlameItem {
+                i: (lnum as usize).saturating_sub(1),
+                author: commit.author,
+                time: commit.time,
+            })
+            .collect();
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .context("invalid system time")?
+            .as_secs();
+
+        if let Some(state) = self.states.get_mut(&key) {
+            state.blame.sync(state.len, items, now, levels);
+        }
+
+        self.redraw()
+    }
+
+    /// ask the language servers for the symbols of the buffer `bufnr`,
+    /// which are sent back as `document_symbol`. false when no server is attached.
+    fn request_document_symbols(&mut self, bufnr: i64, tick: i64) -> Result<bool> {
+        let value = self
+            .exec_lua(
+                lsp::DOCUMENT_SYMBOL,
+                vec![
+                    Value::from(self.channel),
+                    Value::from(bufnr),
+                    Value::from(tick),
+                ],
+            )
+            .context("failed to request document symbols")?;
+
+        Ok(value.as_bool().unwrap_or(false))
+    }
+
+    /// symbols of the file `name` of `len` lines in the first tags file.
+    /// a symbol without its end line lasts until the next one.
+    fn tag_symbols(&mut self, name: &str, len: usize) -> Result<Vec<Symbol>> {
+        let tagfile = self
+            .nvim
+            .call_function("tagfiles", vec![])
+            .context("failed to get tags files")?
+            .as_array()
+            .and_then(|files| files.first())
+            .and_then(|file| file.as_str())
+            .unwrap_or("")
+            .to_owned();
+
+        let path = self.report_paths(vec![tagfile])?.remove(0);
+
+        if let Err(err) = self.tags.update(&path, Tags::parse) {
+            eprintln!("err: {:#}", err);
+        }
+
+        let tags = match self.tags.get() {
+            Some(tags) => tags,
+            None => return Ok(vec![]),
+        };
+
+        let mut entries = tags
+            .entries(name)
+            .into_iter()
+            .filter_map(|entry| tag_kind(&entry.kind).map(|kind| (entry, kind)))
+            .collect::<Vec<_>>();
+
+        entries.sort_by_key(|(entry, _)| entry.lnum);
+
+        let starts = entries
+            .iter()
+            .map(|(entry, _)| entry.lnum)
+            .collect::<Vec<_>>();
+
+        Ok(entries
+            .iter()
+            .map(|(entry, kind)| {
+                let end = entry.end.unwrap_or_else(|| {
+                    starts
+                        .iter()
+                        .find(|start| **start > entry.lnum)
+                        .map_or(len as u64, |start| start - 1)
+                });
+
+                Symbol {
+                    start: (entry.lnum as usize).saturating_sub(1),
+                    end: (end as usize).saturating_sub(1),
+                    kind: kind.to_string(),
+                    name: entry.name.to_string(),
+                }
+            })
+            .collect())
+    }
+
+    /// highlight the lines containing the word under the cursor,
+    /// or its references when a language server knows them
+    fn occurrence(&mut self, values: Vec<Value>) -> Result<()> {
+        let word = values
+            .first()
+            .and_then(|value| value.as_str())
+            .context("invalid word")?
+            .to_owned();
+
+        let cur_buf = self
+            .nvim
+            .get_current_buf()
+            .context("failed to get current buffer")?;
+
+        let key = cur_buf
+            .get_number(&mut self.nvim)
+            .context("failed to get buffer number")?;
+
+        let tick = cur_buf
+            .get_changedtick(&mut self.nvim)
+            .context("failed to get changedtick")?;
+
+        let occurrence_key = Some((tick, word.clone()));
+
+        match self.states.get(&key) {
+            Some(state) if state.occurrence_key == occurrence_key => return Ok(()),
+            Some(_) => {}
+            None => return Ok(()),
+        }
+
+        // the references are drawn when the language servers answer
+        let requested = !word.is_empty() && self.request_document_highlights(key, tick, &word)?;
+
+        if let Some(state) = self.states.get_mut(&key) {
+            if !requested {
+                state.occurrence.sync(&state.lines, &word);
+            }
+
+            state.occurrence_key = occurrence_key;
+        }
+
+        if requested {
+            return Ok(());
+        }
+
+        self.redraw()
+    }
+
+    /// draw the references answered by the language servers,
+    /// or the occurrences of the word when there is none
+    fn document_highlight(&mut self, values: Vec<Value>) -> Result<()> {
+        let payload = DocumentHighlightPayload::try_from(values).context("invalid payload")?;
+
+        let state = match self.states.get_mut(&payload.bufnr) {
+            Some(state) => state,
+            None => return Ok(()),
+        };
+
+        // the cursor has moved to another word or the buffer has changed since the request
+        if state.occurrence_key != Some((payload.tick, payload.word.clone())) {
+            return Ok(());
+        }
+
+        if payload.highlights.is_empty() {
+            state.occurrence.sync(&state.lines, &payload.word);
+        } else {
+            let occurrences = payload.highlights.iter().map(to_occurrence).collect();
+
+            state.occurrence.sync_ranges(state.len, occurrences);
+        }
+
+        self.redraw()
+    }
+
+    /// ask the language servers for the references of the symbol under the cursor,
+    /// which are sent back as `document_highlight`. false when no server is attached.
+    fn request_document_highlights(&mut self, bufnr: i64, tick: i64, word: &str) -> Result<bool> {
+        let value = self
+            .exec_lua(
+                lsp::DOCUMENT_HIGHLIGHT,
+                vec![
+                    Value::from(self.channel),
+                    Value::from(bufnr),
+                    Value::from(tick),
+                    Value::from(word),
+                ],
+            )
+            .context("failed to request document highlights")?;
+
+        Ok(value.as_bool().unwrap_or(false))
+    }
+
+    /// lines of the file `name` in the coverage report at `path`, which is reloaded when it has changed
+    fn coverage_items(&mut self, path: &str, name: &str) -> Vec<CoverageItem> {
+        if let Err(err) = self.coverage.update(path, CoverageReport::parse) {
+            eprintln!("err: {:#}", err);
+        }
+
+        self.coverage
+            .get()
+            .map(|report| report.lines(name).iter().map(to_coverage_item).collect())
+            .unwrap_or_default()
+    }
+
+    /// samples of the lines of the file `name` in the profile at `path`, which is reloaded when it has changed
+    fn profile_items(&mut self, path: &str, name: &str) -> Vec<ProfileItem> {
+        if let Err(err) = self.profile.update(path, Profile::parse) {
+            eprintln!("err: {:#}", err);
+        }
+
+        self.profile
+            .get()
+            .map(|profile| {
+                profile
+                    .lines(name)
+                    .into_iter()
+                    .map(|(lnum, samples)| ProfileItem {
+                        i: (lnum as usize).saturating_sub(1),
+                        samples,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// failures located in the file `name` by the test results at `path`,
+    /// which are reloaded when they have changed
+    fn test_diagnostics(&mut self, path: &str, name: &str) -> Vec<Diagnostic> {
+        if let Err(err) = self.test_results.update(path, TestResults::parse) {
+            eprintln!("err: {:#}", err);
+        }
+
+        self.test_results
+            .get()
+            .map(|results| {
+                results
+                    .failures(name)
+                    .into_iter()
+                    .map(to_test_diagnostic)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// absolute paths of report files, given relative to the working directory of neovim,
+    /// expanded in a single call. empty paths are left empty.
+    fn report_paths(&mut self, paths: Vec<String>) -> Result<Vec<String>> {
+        let given = paths
+            .iter()
+            .filter(|path| !path.is_empty())
+            .map(|path| Value::from(&path[..]))
+            .collect::<Vec<_>>();
+
+        if given.is_empty() {
+            return Ok(paths);
+        }
+
+        let expanded = self
+            .nvim
+            .call_function(
+                "map",
+                vec![Value::from(given), Value::from("fnamemodify(v:val, ':p')")],
+            )
+            .context("failed to expand report paths")?;
+
+        let mut expanded = expanded.as_array().context("invalid report paths")?.iter();
+
+        paths
+            .into_iter()
+            .map(|path| {
+                if path.is_empty() {
+                    return Ok(path);
+                }
+
+                expanded
+                    .next()
+                    .and_then(|path| path.as_str())
+                    .map(|path| path.to_owned())
+                    .context("invalid report path")
+            })
+            .collect()
+    }
+
+    /// marks placed in the buffer `bufnr`, both local and global, only collected while the
+    /// marks layer is enabled
+    fn get_marks(&mut self, bufnr: i64) -> Result<Vec<Mark>> {
+        if !self.config.is_enabled("marks") {
+            return Ok(vec![]);
+        }
+
+        let local = self
+            .nvim
+            .call_function("getmarklist", vec![Value::from(bufnr)])
+            .context("failed to get local marks")?;
+        let global = self
+            .nvim
+            .call_function("getmarklist", vec![])
+            .context("failed to get global marks")?;
+
+        let entries = local
+            .as_array()
+            .into_iter()
+            .chain(global.as_array())
+            .flatten()
+            .map(MarkEntry::try_from)
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(entries
+            .iter()
+            .filter(|entry| entry.bufnr as i64 == bufnr && entry.lnum > 0)
+            .filter(|entry| matches!(entry.name, 'a'..='z' | 'A'..='Z' | '.' | '^' | '[' | ']'))
+            .map(to_mark)
+            .collect())
+    }
+
+    /// signs placed in the buffer `bufnr` by `sign_place()` or with extmarks,
+    /// only collected while the layer of some sign rule is enabled
+    fn get_signs(&mut self, bufnr: i64) -> Result<Vec<PlacedSign>> {
+        if !self.config.is_rule_enabled("sign") {
+            return Ok(vec![]);
+        }
+
+        let placed = self
+            .nvim
+            .call_function(
+                "sign_getplaced",
+                vec![
+                    Value::from(bufnr),
+                    Value::from(vec![(Value::from("group"), Value::from("*"))]),
+                ],
+            )
+            .context("failed to get placed signs")?;
+
+        let extmarks = self.exec_lua(EXTMARK_SIGNS, vec![])?;
+
+        // `[{bufnr, signs}]`
+        placed
+            .as_array()
+            .and_then(|buffers| buffers.first())
+            .and_then(|buffer| buffer.as_map())
+            .and_then(|fields| {
+                fields
+                    .iter()
+                    .find(|field| field.0.as_str() == Some("signs"))
+            })
+            .and_then(|field| field.1.as_array())
+            .into_iter()
+            .chain(extmarks.as_array())
+            .flatten()
+            .map(PlacedSign::try_from)
+            .collect()
+    }
+
+    /// column from which lines of the current buffer are too long, and its `tabstop`
+    fn get_text_limit(&mut self) -> Result<(usize, usize)> {
+        let options = self
+            .nvim
+            .eval("[&textwidth, &colorcolumn, &tabstop]")
+            .context("failed to eval text width options")?;
+
+        let options = options.as_array().context("invalid text width options")?;
+
+        let textwidth = options[0].as_u64().context("invalid textwidth")? as usize;
+        let colorcolumn = options[1].as_str().context("invalid colorcolumn")?;
+        let tabstop = options[2].as_u64().context("invalid tabstop")? as usize;
+
+        Ok((text_limit(textwidth, colorcolumn), tabstop))
+    }
+
+    /// misspelled words of all the lines of the buffer `key` when `spelllang` has changed,
+    /// which is empty while `spell` is off
+    fn rescan_spell(&mut self, key: i64, spelllang: &str) -> Result<Option<Vec<Vec<SpellItem>>>> {
+        let lines = match self.states.get(&key) {
+            Some(state) if state.spelllang != spelllang => state.lines.clone(),
+            _ => return Ok(None),
+        };
+
+        if spelllang.is_empty() {
+            return Ok(Some(vec![]));
+        }
+
+        // the layer stays empty when spell checking is not available
+        Ok(Some(self.spell_check(key, &lines)?.unwrap_or_default()))
+    }
+
+    /// misspelled words of each of `lines` in the buffer `bufnr`,
+    /// or `None` when spell checking is not available
+    fn spell_check(&mut self, bufnr: i64, lines: &[String]) -> Result<Option<Vec<Vec<SpellItem>>>> {
+        let lines: Vec<_> = lines.iter().map(|line| Value::from(&line[..])).collect();
+
+        let result = self.exec_lua(SPELL_CHECK, vec![Value::from(bufnr), Value::from(lines)])?;
+
+        let results = match result.as_array() {
+            Some(results) => results,
+            None => return Ok(None),
+        };
+
+        results
+            .iter()
+            .map(|words| {
+                words
+                    .as_array()
+                    .context("invalid spell words")?
+                    .iter()
+                    .map(|word| BadWord::try_from(word).map(|word| to_spell_item(&word)))
+                    .collect()
+            })
+            .collect::<Result<_>>()
+            .map(Some)
+    }
+
+    /// extmarks of the current buffer in the namespaces of the extmark rules,
+    /// only collected while the layer of some extmark rule is enabled
+    fn get_extmarks(&mut self) -> Result<Vec<Extmark>> {
+        if !self.config.is_rule_enabled("extmark") {
+            return Ok(vec![]);
+        }
+
+        let namespaces = self
+            .config
+            .extmarks
+            .iter()
+            .map(|rule| Value::from(rule.namespace.clone()))
+            .collect::<Vec<_>>();
+
+        let extmarks = self.exec_lua(EXTMARKS, vec![Value::from(namespaces)])?;
+
+        extmarks
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(Extmark::try_from)
+            .collect()
+    }
 
-fn to_change(hunk: &Hunk) -> Change {
-    Change {
-        i: hunk.lnum as usize - 1,
-        len: hunk.len,
+    /// lines of the buffer `bufnr` in the `[entries, index]` list returned by `function`,
+    /// like `getjumplist()` and `getchangelist()`, ordered from the oldest
+    fn get_positions(
+        &mut self,
+        function: &str,
+        args: Vec<Value>,
+        bufnr: i64,
+    ) -> Result<Vec<usize>> {
+        let list = self
+            .nvim
+            .call_function(function, args)
+            .with_context(|| format!("failed to call {}", function))?;
+
+        let entries = list
+            .as_array()
+            .and_then(|list| list.first())
+            .and_then(|entries| entries.as_array())
+            .context("invalid position list")?
+            .iter()
+            .map(PositionEntry::try_from)
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(entries
+            .iter()
+            .filter(|entry| entry.bufnr == 0 || entry.bufnr as i64 == bufnr)
+            .filter(|entry| entry.lnum > 0)
+            .map(|entry| entry.lnum as usize - 1)
+            .collect())
     }
-}
 
-#[async_trait]
-impl ServerTrait for Server {
-    async fn start(&mut self, done: mpsc::Receiver<()>) -> Result<()> {
-        let recv = self.nvim.session.start_event_loop_channel();
+    /// describe the buffer lines drawn on the hovered map row,
+    /// given as a 1-based line of the map window
+    fn hover(&mut self, values: Vec<Value>) -> Result<()> {
+        let row = values
+            .first()
+            .and_then(|value| value.as_u64())
+            .context("invalid hover row")?;
 
-        eprintln!("start event loop");
+        let map_win = match &self.win {
+            Some(win) => win.clone(),
+            None => return Ok(()),
+        };
 
-        self.buf = Some(
-            self.nvim
-                .create_buf(false, true)
-                .context("failed to create buf")?,
-        );
+        let win_height = map_win
+            .get_height(&mut self.nvim)
+            .context("failed to get window height")? as u64;
 
-        loop {
-            if Err(mpsc::TryRecvError::Empty) != done.try_recv() {
-                break;
-            }
+        if row == 0 || row > win_height {
+            return self.unhover(vec![]);
+        }
 
-            match recv.try_recv() {
-                Err(mpsc::TryRecvError::Empty) => {}
-                Err(mpsc::TryRecvError::Disconnected) => {
-                    eprintln!("neovim has disconeccted");
-                    break;
-                }
-                Ok((event, values)) => {
-                    let result = match Message::from(event) {
-                        Message::Sync => self.sync(values).context("failed to call sync handler"),
-                        Message::Show => self.show(values).context("failed to call show handler"),
-                        Message::Resize => {
-                            self.resize(values).context("failed to call resize handler")
-                        }
-                        Message::Close => {
-                            self.close(values).context("failed to call close handler")
-                        }
-                        _ => {
-                            eprintln!("unknown message");
-                            Ok(())
+        let key = self
+            .nvim
+            .get_current_buf()
+            .context("failed to get current buffer")?
+            .get_number(&mut self.nvim)
+            .context("failed to get buffer number")?;
+
+        let mut texts = vec![];
+
+        if let Some(state) = self.states.get(&key) {
+            let lines = Picomap::lines(row - 1, self.folds.display_len(state.len), win_height);
+
+            for i in self.folds.lines(lines) {
+                for layer in self.config.layers.iter().filter(|layer| layer.enabled) {
+                    if let Some(highlighter) = state.layer(&layer.name) {
+                        for text in highlighter.describe(i) {
+                            texts.push(format!("{}: {}", i + 1, text));
                         }
-                    };
-                    if let Err(err) = result {
-                        eprintln!("err: {}", err);
                     }
                 }
             }
+        }
 
-            smol::Timer::new(Duration::from_millis(10)).await;
+        if texts.is_empty() {
+            return self.unhover(vec![]);
         }
 
-        eprintln!("exit event loop");
+        // the popup is no taller than the map, with the rest counted in the last line
+        let max_height = max(win_height as usize, 1);
 
-        Ok(())
-    }
-}
+        if texts.len() > max_height {
+            let more = texts.len() - (max_height - 1);
 
-impl Server {
-    fn sync(&mut self, values: Vec<Value>) -> Result<()> {
-        let payload = SyncPayload::try_from(values).context("invalid payload")?;
+            texts.truncate(max_height - 1);
+            texts.push(format!("…{} more", more));
+        }
 
-        let diags = payload.locations.iter().map(to_diagnostic).collect();
-        let changes = payload.hunks.iter().map(to_change).collect();
+        let buf = match &self.hover_buf {
+            Some(buf) => buf.clone(),
+            None => {
+                let buf = self
+                    .nvim
+                    .create_buf(false, true)
+                    .context("failed to create hover buf")?;
+                self.hover_buf = Some(buf.clone());
+                buf
+            }
+        };
 
-        let cur_buf = self
-            .nvim
-            .get_current_buf()
-            .context("failed to get current buffer")?;
+        let width = texts
+            .iter()
+            .map(|text| text.chars().count())
+            .max()
+            .unwrap_or(0) as u64;
+        let height = texts.len() as u64;
 
-        let buf_len = cur_buf
-            .line_count(&mut self.nvim)
-            .context("failed to get line count")? as usize;
+        buf.set_lines(&mut self.nvim, 0, -1, false, texts)
+            .context("failed to set hover lines")?;
 
-        self.diags.sync(buf_len, diags);
-        self.changes.sync(buf_len, changes);
-        self.picomap.modifier = self.get_modifier()?;
-        self.buf_len = buf_len;
+        // moved up so that the popup ends at the bottom of the map at most
+        let row = min(row - 1, win_height.saturating_sub(height));
 
-        self.redraw()
+        let config =
+            self.config
+                .to_hover_config(map_win.get_value(), row, width, height, self.side());
+
+        match &self.hover_win {
+            Some(win) => win
+                .set_config(&mut self.nvim, config)
+                .context("failed to set hover window config")?,
+            None => {
+                let win = self
+                    .nvim
+                    .open_win(&buf, false, config)
+                    .context("failed to create hover win")?;
+
+                win.set_option(&mut self.nvim, "winhl", Value::from("Normal:Picomap"))
+                    .context("failed to set winhl option to hover win")?;
+
+                self.hover_win = Some(win);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn unhover(&mut self, _values: Vec<Value>) -> Result<()> {
+        let win = match self.hover_win.take() {
+            Some(win) => win,
+            None => return Ok(()),
+        };
+
+        win.close(&mut self.nvim, true)
+            .context("failed to close hover")
     }
 
+    /// open the map, which stays hidden while the current window is excluded
     fn show(&mut self, _values: Vec<Value>) -> Result<()> {
+        if self.win.is_some() || self.hidden {
+            return Ok(());
+        }
+
+        self.hidden = true;
+
+        self.exclude()
+    }
+
+    fn open_win(&mut self) -> Result<()> {
         let cur_win = self
             .nvim
             .get_current_win()
@@ -145,6 +1495,8 @@ impl Server {
 
         let config = self.get_win_config(&cur_win)?;
 
+        self.apply_colors()?;
+
         let buf = match &self.buf {
             Some(buf) => buf,
             None => return Ok(()),
@@ -161,11 +1513,6 @@ impl Server {
                 .context("failed to create win")?,
         );
 
-        let winblend = self
-            .nvim
-            .get_var("picomap_winblend")
-            .context("failed to get global winblend option")?;
-
         let win = match &self.win {
             Some(win) => win,
             None => return Ok(()),
@@ -173,12 +1520,15 @@ impl Server {
 
         win.set_option(&mut self.nvim, "winhl", Value::from("Normal:Picomap"))
             .context("failed to set winhl option to win")?;
+        let winblend = Value::from(self.winblend());
         win.set_option(&mut self.nvim, "winblend", winblend)
             .context("failed to set winblend option to win")?;
 
         buf.set_option(&mut self.nvim, "filetype", Value::from("picomap"))
             .context("failed to set filetype option")?;
 
+        self.apply_matches()?;
+
         self.nvim
             .set_current_win(&cur_win)
             .context("failed to set current win")?;
@@ -187,6 +1537,8 @@ impl Server {
     }
 
     fn resize(&mut self, _values: Vec<Value>) -> Result<()> {
+        self.exclude()?;
+
         let cur_win = self
             .nvim
             .get_current_win()
@@ -204,10 +1556,68 @@ impl Server {
 
         self.picomap.modifier = self.get_modifier()?;
 
+        self.dodge()?;
+
         self.redraw()
     }
 
+    fn configure(&mut self, values: Vec<Value>) -> Result<()> {
+        let value = values.first().context("missing config value")?;
+
+        self.config.update(value).context("invalid config")?;
+        self.states.set_capacity(self.config.max_buffers as usize);
+        self.blames
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .set_capacity(self.config.max_buffers as usize);
+
+        for state in self.states.values_mut() {
+            state
+                .annotation
+                .sync(&state.lines, &self.config.annotations);
+            state.sync_rules(&self.config.rules);
+        }
+
+        self.apply_colors()?;
+        self.apply_matches()?;
+
+        self.update_visibility()?;
+
+        let win = match &self.win {
+            Some(win) => win.clone(),
+            None => return Ok(()),
+        };
+
+        let winblend = Value::from(self.winblend());
+
+        win.set_option(&mut self.nvim, "winblend", winblend)
+            .context("failed to set winblend option to win")?;
+
+        self.resize(vec![])
+    }
+
+    fn evict(&mut self, values: Vec<Value>) -> Result<()> {
+        let key = values
+            .first()
+            .and_then(|value| value.as_i64())
+            .context("invalid buffer number")?;
+
+        self.states.remove(&key);
+
+        Ok(())
+    }
+
     fn close(&mut self, _values: Vec<Value>) -> Result<()> {
+        self.dodging = Dodge::None;
+        self.excluded = false;
+        self.hidden = false;
+
+        self.close_win()
+    }
+
+    fn close_win(&mut self) -> Result<()> {
+        self.unhover(vec![])?;
+
         let win = match &self.win {
             Some(win) => win,
             None => return Ok(()),
@@ -221,20 +1631,262 @@ impl Server {
         Ok(())
     }
 
-    fn redraw(&mut self) -> Result<()> {
+    /// close the map while it is excluded or dodging by hiding,
+    /// and reopen it once neither applies
+    fn update_visibility(&mut self) -> Result<()> {
+        let hiding = self.excluded || self.dodging == Dodge::Hide;
+
+        if !hiding && self.hidden {
+            self.hidden = false;
+
+            return self.open_win();
+        }
+
+        if hiding && self.win.is_some() {
+            self.hidden = true;
+
+            return self.close_win();
+        }
+
+        Ok(())
+    }
+
+    /// check the current window against the filetype, buftype and size rules
+    fn exclude(&mut self) -> Result<()> {
+        if self.win.is_none() && !self.hidden {
+            return Ok(());
+        }
+
+        let cur_win = self
+            .nvim
+            .get_current_win()
+            .context("failed to get current win")?;
+
+        let info = self.get_win_info(&cur_win)?;
+
+        let cur_buf = cur_win
+            .get_buf(&mut self.nvim)
+            .context("failed to get current buffer")?;
+
+        let filetype = cur_buf
+            .get_option(&mut self.nvim, "filetype")
+            .context("failed to get filetype option")?;
+        let buftype = cur_buf
+            .get_option(&mut self.nvim, "buftype")
+            .context("failed to get buftype option")?;
+        let line_count = cur_buf
+            .line_count(&mut self.nvim)
+            .context("failed to get line count")? as u64;
+
+        self.excluded = !self.config.allows(
+            filetype.as_str().unwrap_or(""),
+            buftype.as_str().unwrap_or(""),
+            &info,
+            line_count,
+        );
+
+        self.update_visibility()
+    }
+
+    fn winblend(&self) -> u64 {
+        if self.dodging == Dodge::Blend {
+            self.config.dodge_winblend
+        } else {
+            self.config.winblend
+        }
+    }
+
+    fn side(&self) -> Side {
+        if self.dodging == Dodge::Move {
+            self.config.side.opposite()
+        } else {
+            self.config.side
+        }
+    }
+
+    /// blend, move or hide the map while it covers the cursor or text,
+    /// and restore it once the overlap is gone
+    fn dodge(&mut self) -> Result<()> {
+        if self.excluded || (self.win.is_none() && !self.hidden) {
+            return Ok(());
+        }
+
         let cur_win = self
             .nvim
             .get_current_win()
-            .context("failed to get window")?;
+            .context("failed to get current win")?;
+
+        let info = self.get_win_info(&cur_win)?;
+
+        let extent = TextExtent::try_from(
+            &self
+                .nvim
+                .call_function("picomap#extent", vec![])
+                .context("failed to get text extent")?,
+        )?;
+
+        let side = self.config.side;
+
+        let dodging = match self.config.dodge {
+            _ if !self.config.overlaps(&info, &extent, side) => Dodge::None,
+            // the map blends in instead when the other side is covered too
+            Dodge::Move if self.config.overlaps(&info, &extent, side.opposite()) => Dodge::Blend,
+            dodge => dodge,
+        };
+
+        if dodging == self.dodging {
+            return Ok(());
+        }
+
+        let previous = std::mem::replace(&mut self.dodging, dodging);
+
+        match (previous, dodging) {
+            (Dodge::Hide, _) | (_, Dodge::Hide) => self.update_visibility(),
+            _ => {
+                let win = match &self.win {
+                    Some(win) => win.clone(),
+                    None => return Ok(()),
+                };
+
+                let config = self.get_win_config(&cur_win)?;
+
+                win.set_config(&mut self.nvim, config)
+                    .context("failed to set window config")?;
+                let winblend = Value::from(self.winblend());
+                win.set_option(&mut self.nvim, "winblend", winblend)
+                    .context("failed to set winblend option to win")
+            }
+        }
+    }
+
+    /// id of the channel of this server from `nvim_get_api_info()`
+    fn get_channel(&mut self) -> Result<u64> {
+        self.nvim
+            .session
+            .call("nvim_get_api_info", vec![])?
+            .as_array()
+            .and_then(|info| info.first())
+            .and_then(|id| id.as_u64())
+            .context("invalid api info")
+    }
+
+    fn exec_lua(&mut self, code: &str, args: Vec<Value>) -> Result<Value> {
+        Ok(self
+            .nvim
+            .session
+            .call("nvim_exec_lua", vec![Value::from(code), Value::from(args)])?)
+    }
+
+    fn load_config(&mut self) -> Result<Config> {
+        let value = match self.nvim.get_var("picomap_config") {
+            Ok(value) => value,
+            Err(_) => return Ok(Config::default()),
+        };
+
+        Config::try_from(&value).context("invalid g:picomap_config")
+    }
+
+    fn apply_colors(&mut self) -> Result<()> {
+        for command in self.config.to_commands() {
+            self.nvim
+                .command(&command)
+                .context("failed to define highlight")?;
+        }
+
+        Ok(())
+    }
+
+    fn apply_matches(&mut self) -> Result<()> {
+        let win = match &self.win {
+            Some(win) => win.get_value().clone(),
+            None => return Ok(()),
+        };
+
+        self.nvim
+            .call_function("clearmatches", vec![win.clone()])
+            .context("failed to clear matches")?;
+
+        let dict = Value::Map(vec![(Value::from("window"), win)]);
 
-        let win_height = cur_win
+        for (group, pattern) in self.config.to_matches() {
+            self.nvim
+                .call_function(
+                    "matchadd",
+                    vec![
+                        Value::from(group),
+                        Value::from(pattern),
+                        Value::from(10),
+                        Value::from(-1),
+                        dict.clone(),
+                    ],
+                )
+                .context("failed to add match")?;
+        }
+
+        Ok(())
+    }
+
+    fn redraw(&mut self) -> Result<()> {
+        // the map may be shorter than the current window when it has a border
+        let map_win = match &self.win {
+            Some(win) => win.clone(),
+            None => self
+                .nvim
+                .get_current_win()
+                .context("failed to get window")?,
+        };
+
+        let win_height = map_win
             .get_height(&mut self.nvim)
             .context("failed to get window height")? as u64;
 
-        self.picomap.changes = self.changes.highlight();
-        self.picomap.diags = self.diags.highlight();
+        let key = self
+            .nvim
+            .get_current_buf()
+            .context("failed to get current buffer")?
+            .get_number(&mut self.nvim)
+            .context("failed to get buffer number")?;
+
+        let folds = &self.folds;
+
+        let buf_len = match self.states.get(&key) {
+            Some(state) => {
+                let layers = self
+                    .config
+                    .layers
+                    .iter()
+                    .zip(self.config.offsets())
+                    .filter(|(layer, _)| layer.enabled)
+                    .map(|(layer, offset)| {
+                        let highlights = match &layer.name[..] {
+                            "fold" => folds.markers(state.len),
+                            name => folds.collapse(
+                                state
+                                    .layer(name)
+                                    .map(|highlighter| highlighter.highlight())
+                                    .unwrap_or_default(),
+                            ),
+                        };
+
+                        let highlights = highlights
+                            .into_iter()
+                            .map(|level| if level > 0 { offset + level } else { 0 })
+                            .collect();
+
+                        (layer.column as usize, highlights)
+                    })
+                    .collect();
+
+                let len = folds.display_len(state.len);
 
-        let buffer = self.picomap.to_strings(self.buf_len, win_height);
+                self.picomap.columns = Picomap::compose(self.config.width as usize, len, layers);
+
+                len
+            }
+            None => 0,
+        };
+
+        let buffer = self.picomap.to_strings(buf_len, win_height);
 
         let buf = match &self.buf {
             Some(buf) => buf,
@@ -253,13 +1905,9 @@ impl Server {
             .get_current_win()
             .context("failed to get current window")?;
 
-        let win_height = cur_win
-            .get_height(&mut self.nvim)
-            .context("failed to get window height")? as u64;
-
         let mode = self
             .nvim
-            .eval("mode()")
+            .eval("mode(1)")
             .context("failed to eval mode")?
             .as_str()
             .context("invalid mode str")?
@@ -286,48 +1934,87 @@ impl Server {
             .context("failed to get cursor")?
             .0 as u64;
 
+        self.folds = self.get_folds()?;
+
+        // the window height overstates the visible lines when they wrap or fold
+        let bottom = self
+            .nvim
+            .eval("line('w$')")
+            .context("failed to eval last visible line")?
+            .as_u64()
+            .context("invalid last visible line")?;
+
+        let display = |line: u64| self.folds.display(line as usize - 1) as u64;
+
         let visible_frame = Frame {
-            top: scroll - 1,
-            bottom: scroll - 1 + win_height,
+            top: display(scroll),
+            bottom: display(bottom),
         };
 
-        let mut modifier = Modifier::new(cursor - 1, visible_frame);
+        let mut modifier = Modifier::new(display(cursor), visible_frame);
 
-        modifier.select_frame = match &mode[..] {
-            "v" | "V" | "CTRL-V" => Some(Frame {
-                top: select_start - 1,
-                bottom: cursor - 1,
-            }),
-            _ => None,
-        };
+        if let Some((selection, pending)) = Selection::from_mode(&mode) {
+            // the range of a pending operator is unknown until its motion,
+            // so only the cursor line is marked
+            let top = if pending { cursor } else { select_start };
+
+            modifier.select_frame = Some(Frame {
+                top: display(top),
+                bottom: display(cursor),
+            });
+            modifier.selection = selection;
+        }
 
         Ok(modifier)
     }
 
+    /// closed folds of the current window when the map is drawn in display lines
+    fn get_folds(&mut self) -> Result<Folds> {
+        if self.config.lines != Lines::Folds {
+            return Ok(Folds::default());
+        }
+
+        let folds = self
+            .nvim
+            .call_function("picomap#folds", vec![])
+            .context("failed to get closed folds")?;
+
+        let ranges = folds
+            .as_array()
+            .context("invalid closed folds")?
+            .iter()
+            .map(|fold| {
+                let fold = fold.as_array().context("invalid closed fold")?;
+
+                Ok((
+                    fold[0].as_u64().context("invalid fold start")? as usize - 1,
+                    fold[1].as_u64().context("invalid fold end")? as usize - 1,
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Folds::new(ranges))
+    }
+
     fn get_win_config(&mut self, cur_win: &Window) -> Result<Vec<(Value, Value)>> {
-        let cur_win_height = cur_win
-            .get_height(&mut self.nvim)
-            .context("failed to get current win height")?;
-        let cur_win_width = cur_win
-            .get_width(&mut self.nvim)
-            .context("failed to get current win width")?;
-        let cur_win_pos = cur_win
-            .get_position(&mut self.nvim)
-            .context("failed to get current win pos")?;
-
-        Ok(vec![
-            (Value::from("relative"), Value::from("editor")),
-            (Value::from("anchor"), Value::from("NE")),
-            (Value::from("width"), Value::from(2)),
-            (Value::from("focusable"), Value::from(false)),
-            (Value::from("style"), Value::from("minimal")),
-            (Value::from("height"), Value::from(cur_win_height)),
-            (
-                Value::from("col"),
-                Value::from(cur_win_pos.1 + cur_win_width),
-            ),
-            (Value::from("row"), Value::from(cur_win_pos.0)),
-        ])
+        let info = self.get_win_info(cur_win)?;
+
+        Ok(self
+            .config
+            .to_win_config(cur_win.get_value(), &info, self.side()))
+    }
+
+    fn get_win_info(&mut self, cur_win: &Window) -> Result<WinInfo> {
+        let info = self
+            .nvim
+            .call_function("getwininfo", vec![cur_win.get_value().clone()])
+            .context("failed to get current win info")?;
+
+        WinInfo::try_from(
+            info.as_array()
+                .and_then(|infos| infos.first())
+                .context("missing current win info")?,
+        )
     }
 }
 
@@ -0,0 +1,88 @@
+use crate::message::PlacedSign;
+
+/// signs defined with extmarks of the current buffer as a list of
+/// `{lnum, name, group}`, where the name is the highlight group and the group the namespace
+pub const EXTMARK_SIGNS: &str = r#"
+local ok, marks = pcall(vim.api.nvim_buf_get_extmarks, 0, -1, 0, -1, { details = true, type = 'sign' })
+if not ok then
+  return {}
+end
+
+local namespaces = {}
+for name, id in pairs(vim.api.nvim_get_namespaces()) do
+  namespaces[id] = name
+end
+
+local signs = {}
+
+for _, mark in ipairs(marks) do
+  local details = mark[4] or {}
+
+  if details.sign_text or details.sign_hl_group then
+    table.insert(signs, {
+      lnum = mark[2] + 1,
+      name = details.sign_hl_group or '',
+      group = namespaces[details.ns_id] or '',
+    })
+  end
+end
+
+return signs
+"#;
+
+/// signs drawn by a sign layer, matched by their name and group
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SignRule {
+    pub name: String,
+    pub group: String,
+}
+
+impl SignRule {
+    pub fn matches(&self, sign: &PlacedSign) -> bool {
+        matches_pattern(&self.name, &sign.name) && matches_pattern(&self.group, &sign.group)
+    }
+}
+
+/// whether `value` matches `pattern`, which matches anything when empty
+/// and a prefix with a trailing `*`
+pub fn matches_pattern(pattern: &str, value: &str) -> bool {
+    if pattern.is_empty() {
+        return true;
+    }
+
+    match pattern.strip_suffix('*') {
+        Some(prefix) => value.starts_with(prefix),
+        None => value == pattern,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_rule_matches() {
+        let sign = |name: &str, group: &str| PlacedSign {
+            lnum: 1,
+            name: name.to_string(),
+            group: group.to_string(),
+        };
+
+        let rule = SignRule {
+            name: "Dap*".to_string(),
+            group: String::new(),
+        };
+
+        assert!(rule.matches(&sign("DapBreakpoint", "")));
+        assert!(rule.matches(&sign("DapStopped", "dap")));
+        assert!(!rule.matches(&sign("GitSignsAdd", "gitsigns")));
+
+        let rule = SignRule {
+            name: String::new(),
+            group: "gitsigns".to_string(),
+        };
+
+        assert!(rule.matches(&sign("GitSignsAdd", "gitsigns")));
+        assert!(!rule.matches(&sign("GitSignsAdd", "gitsigns_extmark_signs_")));
+    }
+}
@@ -0,0 +1,25 @@
+/// misspelled words of each line given as the argument by `vim.spell.check()`
+/// in the buffer given as the argument, as a list of `[word, type]` for each
+/// line, or nil when it is not available
+pub const SPELL_CHECK: &str = r#"
+local bufnr, lines = ...
+if not vim.spell then
+  return nil
+end
+
+return vim.api.nvim_buf_call(bufnr, function()
+  local result = {}
+
+  for i, line in ipairs(lines) do
+    local words = {}
+
+    for _, bad in ipairs(vim.spell.check(line)) do
+      table.insert(words, { bad[1], bad[2] })
+    end
+
+    result[i] = words
+  end
+
+  return result
+end)
+"#;
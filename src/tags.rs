@@ -0,0 +1,131 @@
+use crate::watch::same_file;
+use anyhow::Result;
+
+/// a definition in a tags file generated by ctags
+#[derive(Debug, PartialEq)]
+pub struct TagEntry {
+    pub name: String,
+    pub file: String,
+    pub kind: String,
+    pub lnum: u64,
+    /// given by universal-ctags with `--fields=+e`
+    pub end: Option<u64>,
+}
+
+#[derive(Debug, Default)]
+pub struct Tags {
+    entries: Vec<TagEntry>,
+}
+
+impl Tags {
+    /// parse a tags file, keeping the entries whose line is known
+    pub fn parse(text: &str) -> Result<Self> {
+        let entries = text
+            .lines()
+            .filter(|line| !line.starts_with("!_TAG_"))
+            .filter_map(parse_entry)
+            .collect();
+
+        Ok(Tags { entries })
+    }
+
+    /// entries of the file `name`
+    pub fn entries(&self, name: &str) -> Vec<&TagEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| same_file(name, &entry.file))
+            .collect()
+    }
+}
+
+/// `{name}\t{file}\t{address};"\t{fields}`, where the fields hold the kind and `line:`/`end:`
+fn parse_entry(line: &str) -> Option<TagEntry> {
+    let mut head = line.splitn(3, '\t');
+    let name = head.next()?;
+    let file = head.next()?;
+    let rest = head.next()?;
+
+    let (address, fields) = match rest.find(";\"\t") {
+        Some(i) => (&rest[..i], &rest[i + 3..]),
+        None => (rest, ""),
+    };
+
+    let mut kind = "";
+    let mut lnum = address.parse().ok();
+    let mut end = None;
+
+    for field in fields.split('\t') {
+        if let Some(value) = field.strip_prefix("kind:") {
+            kind = value;
+        } else if let Some(value) = field.strip_prefix("line:") {
+            lnum = value.parse().ok();
+        } else if let Some(value) = field.strip_prefix("end:") {
+            end = value.parse().ok();
+        } else if !field.contains(':') && kind.is_empty() {
+            kind = field;
+        }
+    }
+
+    Some(TagEntry {
+        name: name.to_string(),
+        file: file.to_string(),
+        kind: kind.to_string(),
+        lnum: lnum?,
+        end,
+    })
+}
+
+/// label of a ctags kind drawn as an outline band, `None` for the other kinds
+pub fn tag_kind(kind: &str) -> Option<&'static str> {
+    match kind {
+        "n" | "module" => Some("module"),
+        "namespace" => Some("namespace"),
+        "c" | "class" => Some("class"),
+        "implementation" => Some("impl"),
+        "s" | "struct" => Some("struct"),
+        "i" | "interface" => Some("interface"),
+        "g" | "enum" => Some("enum"),
+        "f" | "function" => Some("function"),
+        "P" | "method" => Some("method"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tags_parse() {
+        let tags = Tags::parse(
+            "!_TAG_FILE_FORMAT\t2\t/extended format/
+Server\tsrc/server.rs\t/^pub struct Server {$/;\"\ts\tline:20\tend:32
+main\tsrc/main.rs\t5;\"\tf
+sync\tsrc/server.rs\t/^    fn sync(&mut self) {$/;\"\tkind:method\tline:40
+unknown\tsrc/server.rs\t/^foo$/;\"\tf
+",
+        )
+        .unwrap();
+
+        assert_eq!(
+            tags.entries("/project/src/server.rs"),
+            vec![
+                &TagEntry {
+                    name: "Server".to_string(),
+                    file: "src/server.rs".to_string(),
+                    kind: "s".to_string(),
+                    lnum: 20,
+                    end: Some(32),
+                },
+                &TagEntry {
+                    name: "sync".to_string(),
+                    file: "src/server.rs".to_string(),
+                    kind: "method".to_string(),
+                    lnum: 40,
+                    end: None,
+                },
+            ]
+        );
+        assert_eq!(tags.entries("/project/src/main.rs")[0].lnum, 5);
+    }
+}
@@ -0,0 +1,104 @@
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::Path;
+use std::time::SystemTime;
+
+/// a report file on disk, parsed again whenever its path or modification time changes
+pub struct Watched<T> {
+    path: String,
+    mtime: Option<SystemTime>,
+    value: Option<T>,
+}
+
+impl<T> Default for Watched<T> {
+    fn default() -> Self {
+        Watched {
+            path: String::new(),
+            mtime: None,
+            value: None,
+        }
+    }
+}
+
+impl<T> Watched<T> {
+    pub fn get(&self) -> Option<&T> {
+        self.value.as_ref()
+    }
+
+    /// load `path` with `parse` when it has changed, and return whether it was reloaded.
+    /// a file which cannot be read or parsed is not tried again until it changes.
+    pub fn update<F>(&mut self, path: &str, parse: F) -> Result<bool>
+    where
+        F: FnOnce(&str) -> Result<T>,
+    {
+        if path.is_empty() {
+            let changed = !self.path.is_empty();
+
+            *self = Self::default();
+
+            return Ok(changed);
+        }
+
+        let mtime = fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+
+        if path == self.path && mtime == self.mtime {
+            return Ok(false);
+        }
+
+        self.path = path.to_string();
+        self.mtime = mtime;
+        self.value = None;
+
+        if mtime.is_none() {
+            bail!("report not found: {}", path);
+        }
+
+        let text = fs::read_to_string(path).with_context(|| format!("failed to read {}", path))?;
+
+        self.value = Some(parse(&text).with_context(|| format!("failed to parse {}", path))?);
+
+        Ok(true)
+    }
+}
+
+/// whether a path given by a report points to the file `name`.
+/// reports often give paths relative to the project root.
+pub fn same_file(name: &str, reported: &str) -> bool {
+    let reported = reported.trim_start_matches("./");
+
+    !reported.is_empty() && Path::new(name).ends_with(reported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_file() {
+        assert!(same_file("/home/foo/project/src/main.rs", "src/main.rs"));
+        assert!(same_file("/home/foo/project/src/main.rs", "./src/main.rs"));
+        assert!(same_file(
+            "/home/foo/project/src/main.rs",
+            "/home/foo/project/src/main.rs"
+        ));
+        assert!(!same_file("/home/foo/project/src/main.rs", "in.rs"));
+        assert!(!same_file("/home/foo/project/src/main.rs", "lib/main.rs"));
+        assert!(!same_file("/home/foo/project/src/main.rs", ""));
+    }
+
+    #[test]
+    fn test_watched_update_empty() {
+        let mut watched = Watched::<String>::default();
+
+        assert!(!watched
+            .update("", |_| -> Result<String> { panic!("parsed an empty path") })
+            .unwrap());
+        assert!(watched
+            .update("/nonexistent/report.xml", |_| Ok(String::new()))
+            .is_err());
+        assert!(watched.update("", |_| Ok(String::new())).unwrap());
+        assert!(watched.get().is_none());
+    }
+}
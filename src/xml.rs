@@ -0,0 +1,214 @@
+//! just enough of XML to read the attributes of report files
+
+/// a start, empty-element or end tag
+#[derive(Debug, PartialEq)]
+pub struct Tag<'a> {
+    pub name: &'a str,
+    /// an end tag, which has no attributes
+    pub end: bool,
+    /// an empty-element tag, which has no end tag
+    pub empty: bool,
+    attrs: Vec<(&'a str, String)>,
+}
+
+impl<'a> Tag<'a> {
+    pub fn attr(&self, key: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|attr| attr.0 == key)
+            .map(|attr| &attr.1[..])
+    }
+}
+
+/// start, empty-element and end tags in document order.
+/// comments, CDATA sections, declarations and processing instructions are skipped.
+pub fn tags(text: &str) -> Vec<Tag<'_>> {
+    let mut result = vec![];
+    let mut rest = text;
+
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+
+        if let Some(comment) = rest.strip_prefix("!--") {
+            rest = skip(comment, "-->");
+        } else if let Some(cdata) = rest.strip_prefix("![CDATA[") {
+            rest = skip(cdata, "]]>");
+        } else if rest.starts_with(&['?', '!'][..]) {
+            rest = skip(rest, ">");
+        } else if let Some(end) = rest.strip_prefix('/') {
+            let len = end.find('>').unwrap_or(end.len());
+
+            result.push(Tag {
+                name: end[..len].trim_end(),
+                end: true,
+                empty: false,
+                attrs: vec![],
+            });
+            rest = skip(end, ">");
+        } else {
+            let (tag, remaining) = parse_tag(rest);
+
+            result.push(tag);
+            rest = remaining;
+        }
+    }
+
+    result
+}
+
+fn skip<'a>(text: &'a str, end: &str) -> &'a str {
+    match text.find(end) {
+        Some(i) => &text[i + end.len()..],
+        None => "",
+    }
+}
+
+fn parse_tag(text: &str) -> (Tag<'_>, &str) {
+    let len = text
+        .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+        .unwrap_or(text.len());
+    let name = &text[..len];
+
+    let mut attrs = vec![];
+    let mut empty = false;
+    let mut rest = &text[len..];
+
+    loop {
+        rest = rest.trim_start();
+
+        if let Some(remaining) = rest.strip_prefix("/>") {
+            empty = true;
+            rest = remaining;
+            break;
+        }
+
+        if let Some(remaining) = rest.strip_prefix('>') {
+            rest = remaining;
+            break;
+        }
+
+        let eq = match rest.find('=') {
+            Some(eq) => eq,
+            None => {
+                rest = skip(rest, ">");
+                break;
+            }
+        };
+
+        let key = rest[..eq].trim();
+        let value = rest[eq + 1..].trim_start();
+
+        let quote = match value.chars().next() {
+            Some(quote) if quote == '"' || quote == '\'' => quote,
+            _ => {
+                rest = skip(rest, ">");
+                break;
+            }
+        };
+
+        let value = &value[1..];
+
+        let end = match value.find(quote) {
+            Some(end) => end,
+            None => {
+                rest = "";
+                break;
+            }
+        };
+
+        attrs.push((key, unescape(&value[..end])));
+        rest = &value[end + 1..];
+    }
+
+    (
+        Tag {
+            name,
+            end: false,
+            empty,
+            attrs,
+        },
+        rest,
+    )
+}
+
+fn unescape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+
+    while let Some(i) = rest.find('&') {
+        result.push_str(&rest[..i]);
+        rest = &rest[i..];
+
+        let end = match rest.find(';') {
+            Some(end) => end,
+            None => break,
+        };
+
+        let c = match &rest[1..end] {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            entity => entity
+                .strip_prefix("#x")
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                .and_then(std::char::from_u32),
+        };
+
+        match c {
+            Some(c) => {
+                result.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    result.push_str(rest);
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xml_tags() {
+        let tags = tags(
+            r#"<?xml version="1.0"?>
+<!-- <skipped a="1"/> -->
+<coverage line-rate='0.5'>
+  <class filename="src/a&amp;b.rs" name="a > b">
+    <line number="1" hits="0"/>
+  </class>
+</coverage>"#,
+        );
+
+        let names = tags
+            .iter()
+            .map(|tag| (tag.name, tag.end, tag.empty))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            names,
+            vec![
+                ("coverage", false, false),
+                ("class", false, false),
+                ("line", false, true),
+                ("class", true, false),
+                ("coverage", true, false),
+            ]
+        );
+        assert_eq!(tags[0].attr("line-rate"), Some("0.5"));
+        assert_eq!(tags[1].attr("filename"), Some("src/a&b.rs"));
+        assert_eq!(tags[1].attr("name"), Some("a > b"));
+        assert_eq!(tags[2].attr("hits"), Some("0"));
+        assert_eq!(tags[2].attr("branch"), None);
+    }
+}